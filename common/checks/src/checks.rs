@@ -3,6 +3,8 @@
 multiversx_sc::imports!();
 multiversx_sc::derive_imports!();
 
+use common_structs::{InterestRateModel, PoolParams, BP};
+
 #[multiversx_sc::module]
 pub trait ChecksModule {
     fn require_amount_greater_than_zero(&self, amount: &BigUint) {
@@ -12,4 +14,77 @@ pub trait ChecksModule {
     fn require_non_zero_address(&self, address: &ManagedAddress) {
         require!(!address.is_zero(), "address is zero");
     }
+
+    fn require_valid_pool_params(&self, pool_params: &PoolParams<Self::Api>) {
+        let bp = BigUint::from(BP);
+
+        // the borrow rate divides by both u_optimal and BP - u_optimal
+        require!(
+            pool_params.u_optimal > 0 && pool_params.u_optimal < bp,
+            "u_optimal must be between 0 and BP"
+        );
+        // the deposit rate is scaled by BP - reserve_factor
+        require!(
+            pool_params.reserve_factor <= bp,
+            "reserve_factor must not exceed BP"
+        );
+    }
+
+    fn require_valid_interest_rate_model(
+        &self,
+        model: &InterestRateModel<Self::Api>,
+        pool_params: &PoolParams<Self::Api>,
+    ) {
+        match model {
+            InterestRateModel::JumpRate { u_jump, .. } => {
+                require!(
+                    u_jump > &pool_params.u_optimal && u_jump < &BigUint::from(BP),
+                    "u_jump must be between u_optimal and BP"
+                );
+            }
+            InterestRateModel::Adaptive {
+                u_target,
+                min_base_rate,
+                max_base_rate,
+                ..
+            } => {
+                require!(
+                    u_target > &0 && u_target < &BigUint::from(BP),
+                    "u_target must be between 0 and BP"
+                );
+                require!(
+                    min_base_rate <= max_base_rate,
+                    "min_base_rate must not exceed max_base_rate"
+                );
+            }
+            InterestRateModel::Kinked | InterestRateModel::Fixed { .. } => {}
+        }
+    }
+
+    fn require_valid_liquidation_threshold(&self, liquidation_threshold: &BigUint) {
+        require!(
+            liquidation_threshold > &0 && liquidation_threshold <= &BigUint::from(BP),
+            "liquidation_threshold must be between 0 and BP"
+        );
+    }
+
+    fn require_valid_loan_to_value(
+        &self,
+        loan_to_value: &BigUint,
+        liquidation_threshold: &BigUint,
+    ) {
+        require!(loan_to_value > &0, "loan_to_value value can not be zero");
+        // a position opened at the maximum LTV must not be liquidatable right away
+        require!(
+            loan_to_value < liquidation_threshold,
+            "loan_to_value must be lower than liquidation_threshold"
+        );
+    }
+
+    fn require_valid_liquidation_bonus(&self, liquidation_bonus: &BigUint) {
+        require!(
+            liquidation_bonus > &0 && liquidation_bonus < &BigUint::from(BP),
+            "liquidation_bonus must be between 0 and BP"
+        );
+    }
 }
@@ -3,11 +3,17 @@
 multiversx_sc::imports!();
 multiversx_sc::derive_imports!();
 
+use multiversx_sc::codec::{DecodeErrorHandler, NestedDecodeInput, TopDecodeInput};
+
 pub const BP: u64 = 1_000_000_000_000_000_000;
 pub const MAX_THRESHOLD: u64 = BP / 2;
 pub const MAX_THRESHOLD_ERROR_MSG: &[u8] =
     b"Cannot liquidate more than 50% of Liquidatee's position!";
+/// Decimals of the isolation debt ceilings, in which borrowed amounts are counted against them
+pub const DEBT_CEILING_DECIMALS: u8 = 2;
 pub const SECONDS_PER_YEAR: u64 = 31_536_000;
+pub const SECONDS_PER_ROUND: u64 = 6;
+pub const ROUNDS_PER_YEAR: u64 = SECONDS_PER_YEAR / SECONDS_PER_ROUND;
 
 #[derive(TopEncode, TopDecode, TypeAbi)]
 pub struct PoolParams<M: ManagedTypeApi> {
@@ -18,6 +24,130 @@ pub struct PoolParams<M: ManagedTypeApi> {
     pub reserve_factor: BigUint<M>,
 }
 
+#[derive(TopEncode, TopDecode, NestedEncode, NestedDecode, TypeAbi, Clone, PartialEq, Debug)]
+pub enum InterestRateModel<M: ManagedTypeApi> {
+    /// Two slopes around `u_optimal`, as configured in `PoolParams`
+    Kinked,
+    /// Kinked model with a third, steeper slope above `u_jump`
+    JumpRate {
+        u_jump: BigUint<M>,
+        r_slope3: BigUint<M>,
+    },
+    /// Constant borrow rate, regardless of utilisation
+    Fixed { rate: BigUint<M> },
+    /// Kinked model around `u_target`, whose base rate drifts up while utilisation
+    /// stays above the target and down while it stays below, within the bounds
+    Adaptive {
+        u_target: BigUint<M>,
+        adjustment_speed: BigUint<M>,
+        min_base_rate: BigUint<M>,
+        max_base_rate: BigUint<M>,
+    },
+}
+
+#[derive(TopEncode, TopDecode, TypeAbi)]
+pub struct AdaptiveRateState<M: ManagedTypeApi> {
+    pub base_rate: BigUint<M>,
+    pub u_current: BigUint<M>,
+    pub u_target: BigUint<M>,
+    pub last_update_round: u64,
+}
+
+/// Live metrics of one market, as listed by the `getMarkets` view.
+/// A cap of zero means the market is uncapped. The loan to value and liquidation threshold
+/// differ from their targets while a ramp of the asset's risk parameters is in progress.
+#[derive(TopEncode, TopDecode, NestedEncode, NestedDecode, TypeAbi)]
+pub struct MarketOverview<M: ManagedTypeApi> {
+    pub asset_id: TokenIdentifier<M>,
+    pub pool_address: ManagedAddress<M>,
+    pub reserves: BigUint<M>,
+    pub total_borrow: BigUint<M>,
+    pub capital_utilisation: BigUint<M>,
+    pub borrow_rate: BigUint<M>,
+    pub deposit_rate: BigUint<M>,
+    pub loan_to_value: BigUint<M>,
+    pub liquidation_bonus: BigUint<M>,
+    pub liquidation_threshold: BigUint<M>,
+    pub target_loan_to_value: BigUint<M>,
+    pub target_liquidation_threshold: BigUint<M>,
+    pub price: Option<BigUint<M>>,
+    pub supply_cap: BigUint<M>,
+    pub borrow_cap: BigUint<M>,
+    pub can_borrow: bool,
+    pub can_be_collateral: bool,
+    pub is_siloed: bool,
+}
+
+/// Risk configuration of one listed asset, written by the risk admin.
+/// A cap or debt ceiling of zero means none is enforced.
+/// `decimals` are the ones of the token, used to normalise amounts counted against debt ceilings.
+#[derive(TopEncode, TopDecode, NestedEncode, NestedDecode, TypeAbi, Clone)]
+pub struct AssetConfig<M: ManagedTypeApi> {
+    pub loan_to_value: BigUint<M>,
+    pub liquidation_threshold: BigUint<M>,
+    pub liquidation_bonus: BigUint<M>,
+    pub supply_cap: BigUint<M>,
+    pub borrow_cap: BigUint<M>,
+    pub debt_ceiling: BigUint<M>,
+    pub can_borrow: bool,
+    pub can_be_collateral: bool,
+    pub is_isolated: bool,
+    pub is_siloed: bool,
+    pub e_mode_category: u8,
+    pub decimals: u8,
+    /// Ticker the price feed lists the asset under, derived from the token id when empty
+    pub oracle_id: ManagedBuffer<M>,
+}
+
+/// Linear transition of the loan to value and liquidation threshold of an asset, from their
+/// values at `start_timestamp` to the ones in its `AssetConfig`, reached at `end_timestamp`.
+#[derive(TopEncode, TopDecode, NestedEncode, NestedDecode, TypeAbi)]
+pub struct RiskParamsRamp<M: ManagedTypeApi> {
+    pub start_loan_to_value: BigUint<M>,
+    pub start_liquidation_threshold: BigUint<M>,
+    pub start_timestamp: u64,
+    pub end_timestamp: u64,
+}
+
+/// Loan to value and liquidation threshold of an asset in effect now, and the targets of the
+/// ramp in progress. Without one, the targets are the current values and the end is zero.
+#[derive(TopEncode, TopDecode, NestedEncode, NestedDecode, TypeAbi)]
+pub struct AssetRiskParams<M: ManagedTypeApi> {
+    pub loan_to_value: BigUint<M>,
+    pub liquidation_threshold: BigUint<M>,
+    pub target_loan_to_value: BigUint<M>,
+    pub target_liquidation_threshold: BigUint<M>,
+    pub ramp_end_timestamp: u64,
+}
+
+/// Risk parameters shared by a category of correlated assets. They replace the
+/// per-asset ones for accounts whose positions all belong to the category.
+#[derive(TopEncode, TopDecode, NestedEncode, NestedDecode, TypeAbi, Clone)]
+pub struct EModeCategory<M: ManagedTypeApi> {
+    pub loan_to_value: BigUint<M>,
+    pub liquidation_threshold: BigUint<M>,
+    pub liquidation_bonus: BigUint<M>,
+}
+
+#[derive(TopEncode, TopDecode, NestedEncode, NestedDecode, TypeAbi, ManagedVecItem)]
+pub struct PositionValue<M: ManagedTypeApi> {
+    pub token_id: TokenIdentifier<M>,
+    pub amount: BigUint<M>,
+    pub value_in_dollars: BigUint<M>,
+}
+
+/// An account whose health factor is below the requested bound, with its
+/// collaterals and debts valued at current prices. The health factor is in BP precision.
+#[derive(TopEncode, TopDecode, NestedEncode, NestedDecode, TypeAbi)]
+pub struct LiquidationCandidate<M: ManagedTypeApi> {
+    pub account_nonce: u64,
+    pub health_factor: BigUint<M>,
+    pub total_collateral_in_dollars: BigUint<M>,
+    pub total_borrow_in_dollars: BigUint<M>,
+    pub collaterals: ManagedVec<M, PositionValue<M>>,
+    pub debts: ManagedVec<M, PositionValue<M>>,
+}
+
 #[derive(TopEncode, TopDecode, TypeAbi)]
 pub struct IssueData<M: ManagedTypeApi> {
     pub name: ManagedBuffer<M>,
@@ -32,13 +162,48 @@ pub struct TokenAmountPair<M: ManagedTypeApi> {
     pub amount: BigUint<M>,
 }
 
-#[derive(NestedEncode, NestedDecode, TopEncode, TopDecode, TypeAbi, Clone)]
+#[derive(NestedEncode, NestedDecode, TopEncode, TypeAbi, Clone)]
 pub struct DepositPosition<M: ManagedTypeApi> {
     pub token_id: TokenIdentifier<M>,
     pub amount: BigUint<M>,
     pub owner_nonce: u64,
     pub round: u64,
     pub initial_supply_index: BigUint<M>,
+    pub is_collateral: bool,
+}
+
+/// Positions stored before the collateral flag existed decode as collateral.
+impl<M: ManagedTypeApi> TopDecode for DepositPosition<M> {
+    fn top_decode_or_handle_err<I, H>(input: I, h: H) -> core::result::Result<Self, H::HandledErr>
+    where
+        I: TopDecodeInput,
+        H: DecodeErrorHandler,
+    {
+        let mut nested_buffer = input.into_nested_buffer();
+        let token_id = TokenIdentifier::dep_decode_or_handle_err(&mut nested_buffer, h)?;
+        let amount = BigUint::dep_decode_or_handle_err(&mut nested_buffer, h)?;
+        let owner_nonce = u64::dep_decode_or_handle_err(&mut nested_buffer, h)?;
+        let round = u64::dep_decode_or_handle_err(&mut nested_buffer, h)?;
+        let initial_supply_index = BigUint::dep_decode_or_handle_err(&mut nested_buffer, h)?;
+        let is_collateral = if nested_buffer.is_depleted() {
+            true
+        } else {
+            bool::dep_decode_or_handle_err(&mut nested_buffer, h)?
+        };
+
+        if !nested_buffer.is_depleted() {
+            return core::result::Result::Err(h.handle_error(DecodeError::INPUT_TOO_LONG));
+        }
+
+        core::result::Result::Ok(DepositPosition {
+            token_id,
+            amount,
+            owner_nonce,
+            round,
+            initial_supply_index,
+            is_collateral,
+        })
+    }
 }
 
 #[derive(NestedEncode, NestedDecode, TopEncode, TopDecode, TypeAbi, Clone)]
@@ -74,6 +239,27 @@ impl<M: ManagedTypeApi> DepositPosition<M> {
             owner_nonce,
             round,
             initial_supply_index,
+            is_collateral: true,
+        }
+    }
+}
+
+impl<M: ManagedTypeApi> AssetConfig<M> {
+    pub fn new(liquidation_threshold: BigUint<M>, decimals: u8) -> Self {
+        AssetConfig {
+            loan_to_value: BigUint::zero(),
+            liquidation_threshold,
+            liquidation_bonus: BigUint::zero(),
+            supply_cap: BigUint::zero(),
+            borrow_cap: BigUint::zero(),
+            debt_ceiling: BigUint::zero(),
+            can_borrow: true,
+            can_be_collateral: true,
+            is_isolated: false,
+            is_siloed: false,
+            e_mode_category: 0,
+            decimals,
+            oracle_id: ManagedBuffer::new(),
         }
     }
 }
@@ -0,0 +1,215 @@
+multiversx_sc::imports!();
+
+use common_structs::{BorrowPosition, DepositPosition, EModeCategory};
+
+#[multiversx_sc::module]
+pub trait EventsModule {
+    #[event("create_market")]
+    fn create_market_event(
+        &self,
+        #[indexed] asset: &TokenIdentifier,
+        #[indexed] pool_address: &ManagedAddress,
+    );
+
+    #[event("enter_market")]
+    fn enter_market_event(&self, #[indexed] caller: &ManagedAddress, #[indexed] account_nonce: u64);
+
+    #[event("exit_market")]
+    fn exit_market_event(&self, #[indexed] caller: &ManagedAddress, #[indexed] account_nonce: u64);
+
+    #[event("add_collateral")]
+    fn add_collateral_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] account_nonce: u64,
+        #[indexed] token_id: &TokenIdentifier,
+        #[indexed] amount: &BigUint,
+        #[indexed] supply_index: &BigUint,
+        #[indexed] borrow_index: &BigUint,
+        position: &DepositPosition<Self::Api>,
+    );
+
+    #[event("remove_collateral")]
+    fn remove_collateral_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] account_nonce: u64,
+        #[indexed] token_id: &TokenIdentifier,
+        #[indexed] amount: &BigUint,
+        #[indexed] supply_index: &BigUint,
+        #[indexed] borrow_index: &BigUint,
+        position: &DepositPosition<Self::Api>,
+    );
+
+    #[event("borrow")]
+    fn borrow_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] account_nonce: u64,
+        #[indexed] token_id: &TokenIdentifier,
+        #[indexed] amount: &BigUint,
+        #[indexed] supply_index: &BigUint,
+        #[indexed] borrow_index: &BigUint,
+        position: &BorrowPosition<Self::Api>,
+    );
+
+    #[event("repay")]
+    fn repay_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] account_nonce: u64,
+        #[indexed] token_id: &TokenIdentifier,
+        #[indexed] amount: &BigUint,
+        #[indexed] supply_index: &BigUint,
+        #[indexed] borrow_index: &BigUint,
+        position: &BorrowPosition<Self::Api>,
+    );
+
+    #[event("update_collateral_usage")]
+    fn update_collateral_usage_event(
+        &self,
+        #[indexed] account_nonce: u64,
+        #[indexed] token_id: &TokenIdentifier,
+        is_collateral: bool,
+    );
+
+    #[event("update_borrow_allowance")]
+    fn update_borrow_allowance_event(
+        &self,
+        #[indexed] account_nonce: u64,
+        #[indexed] delegate: &ManagedAddress,
+        #[indexed] token_id: &TokenIdentifier,
+        amount: &BigUint,
+    );
+
+    #[event("liquidate")]
+    fn liquidate_event(
+        &self,
+        #[indexed] liquidator: &ManagedAddress,
+        #[indexed] liquidatee_account_nonce: u64,
+        #[indexed] token_id: &TokenIdentifier,
+        #[indexed] repaid_amount: &BigUint,
+        #[indexed] seized_amount: &BigUint,
+        #[indexed] supply_index: &BigUint,
+        #[indexed] borrow_index: &BigUint,
+        position: &DepositPosition<Self::Api>,
+    );
+
+    #[event("update_risk_admin")]
+    fn update_risk_admin_event(&self, #[indexed] risk_admin: &ManagedAddress);
+
+    #[event("update_asset_loan_to_value")]
+    fn update_asset_loan_to_value_event(
+        &self,
+        #[indexed] asset: &TokenIdentifier,
+        #[indexed] loan_to_value: &BigUint,
+    );
+
+    #[event("update_asset_liquidation_threshold")]
+    fn update_asset_liquidation_threshold_event(
+        &self,
+        #[indexed] asset: &TokenIdentifier,
+        #[indexed] liquidation_threshold: &BigUint,
+    );
+
+    #[event("ramp_asset_risk_params")]
+    fn ramp_asset_risk_params_event(
+        &self,
+        #[indexed] asset: &TokenIdentifier,
+        #[indexed] target_loan_to_value: &BigUint,
+        #[indexed] target_liquidation_threshold: &BigUint,
+        #[indexed] end_timestamp: u64,
+    );
+
+    #[event("update_asset_liquidation_bonus")]
+    fn update_asset_liquidation_bonus_event(
+        &self,
+        #[indexed] asset: &TokenIdentifier,
+        #[indexed] liquidation_bonus: &BigUint,
+    );
+
+    #[event("update_asset_supply_cap")]
+    fn update_asset_supply_cap_event(
+        &self,
+        #[indexed] asset: &TokenIdentifier,
+        #[indexed] supply_cap: &BigUint,
+    );
+
+    #[event("update_asset_borrow_cap")]
+    fn update_asset_borrow_cap_event(
+        &self,
+        #[indexed] asset: &TokenIdentifier,
+        #[indexed] borrow_cap: &BigUint,
+    );
+
+    #[event("update_asset_can_borrow")]
+    fn update_asset_can_borrow_event(
+        &self,
+        #[indexed] asset: &TokenIdentifier,
+        #[indexed] can_borrow: bool,
+    );
+
+    #[event("update_asset_can_be_collateral")]
+    fn update_asset_can_be_collateral_event(
+        &self,
+        #[indexed] asset: &TokenIdentifier,
+        #[indexed] can_be_collateral: bool,
+    );
+
+    #[event("update_asset_siloed")]
+    fn update_asset_siloed_event(
+        &self,
+        #[indexed] asset: &TokenIdentifier,
+        #[indexed] is_siloed: bool,
+    );
+
+    #[event("update_asset_oracle")]
+    fn update_asset_oracle_event(
+        &self,
+        #[indexed] asset: &TokenIdentifier,
+        #[indexed] oracle_id: &ManagedBuffer,
+        #[indexed] decimals: u8,
+    );
+
+    #[event("update_asset_isolation")]
+    fn update_asset_isolation_event(
+        &self,
+        #[indexed] asset: &TokenIdentifier,
+        #[indexed] is_isolated: bool,
+    );
+
+    #[event("update_asset_debt_ceiling")]
+    fn update_asset_debt_ceiling_event(
+        &self,
+        #[indexed] asset: &TokenIdentifier,
+        #[indexed] debt_ceiling: &BigUint,
+    );
+
+    #[event("update_isolation_borrowable_asset")]
+    fn update_isolation_borrowable_asset_event(
+        &self,
+        #[indexed] asset: &TokenIdentifier,
+        #[indexed] is_borrowable: bool,
+    );
+
+    #[event("update_e_mode_category")]
+    fn update_e_mode_category_event(
+        &self,
+        #[indexed] category_id: u8,
+        category: &EModeCategory<Self::Api>,
+    );
+
+    #[event("update_asset_e_mode_category")]
+    fn update_asset_e_mode_category_event(
+        &self,
+        #[indexed] asset: &TokenIdentifier,
+        #[indexed] category_id: u8,
+    );
+
+    #[event("update_account_e_mode_category")]
+    fn update_account_e_mode_category_event(
+        &self,
+        #[indexed] account_nonce: u64,
+        #[indexed] category_id: u8,
+    );
+}
@@ -3,15 +3,19 @@
 multiversx_sc::imports!();
 multiversx_sc::derive_imports!();
 
+pub mod events;
 pub mod factory;
 mod math;
 mod proxy;
 pub mod router;
 pub mod storage;
 pub mod utils;
+pub mod views;
 
 pub use common_structs::*;
 pub use common_tokens::*;
+use liquidity_pool::liq_storage::ProxyTrait as _;
+use liquidity_pool::liq_utils::ProxyTrait as _;
 use liquidity_pool::liquidity::ProxyTrait as _;
 use multiversx_sc::codec::Empty;
 
@@ -26,10 +30,69 @@ pub trait LendingPool:
     + utils::LendingUtilsModule
     + math::LendingMathModule
     + price_aggregator_proxy::PriceAggregatorModule
+    + events::EventsModule
+    + views::ViewsModule
 {
+    /// `asset_decimals` holds the token decimals of every listed asset and is only
+    /// read when upgrading from storage version 1, which did not record them.
     #[init]
-    fn init(&self, lp_template_address: ManagedAddress) {
+    fn init(
+        &self,
+        lp_template_address: ManagedAddress,
+        asset_decimals: MultiValueEncoded<MultiValue2<TokenIdentifier, u8>>,
+    ) {
         self.liq_pool_template_address().set(&lp_template_address);
+        self.migrate_storage(asset_decimals);
+    }
+
+    fn migrate_storage(&self, asset_decimals: MultiValueEncoded<MultiValue2<TokenIdentifier, u8>>) {
+        let storage_version = self.storage_version().get();
+        require!(
+            storage_version <= storage::STORAGE_VERSION,
+            "cannot downgrade to an older storage version"
+        );
+
+        // versions 0 and 1: per-asset parameters in separate mappers
+        if storage_version < 2 {
+            for (asset, pool_address) in self.pools_map().iter() {
+                let decimals = asset_decimals
+                    .clone()
+                    .into_iter()
+                    .find_map(|pair| {
+                        let (token_id, decimals) = pair.into_tuple();
+                        (token_id == asset).then_some(decimals)
+                    })
+                    .unwrap_or_else(|| sc_panic!("asset decimals not provided for {}", asset));
+                self.migrate_asset_config(&asset, pool_address, decimals);
+            }
+        }
+
+        // version 2: deposit positions stored without the collateral flag. They
+        // decode as collateral and take the current layout the next time they are
+        // stored, so they are migrated on first touch rather than all at once here.
+
+        self.storage_version().set(storage::STORAGE_VERSION);
+    }
+
+    fn migrate_asset_config(
+        &self,
+        asset: &TokenIdentifier,
+        pool_address: ManagedAddress,
+        decimals: u8,
+    ) {
+        let liquidation_threshold: BigUint = self
+            .liquidity_pool_proxy(pool_address)
+            .liquidation_threshold()
+            .execute_on_dest_context();
+
+        // version 1 priced every asset under the ticker of its token identifier
+        let oracle_id = self.get_token_ticker(asset.clone());
+
+        let mut config = AssetConfig::new(liquidation_threshold, decimals);
+        config.loan_to_value = self.legacy_asset_loan_to_value(asset).take();
+        config.liquidation_bonus = self.legacy_asset_liquidation_bonus(asset).take();
+        config.oracle_id = oracle_id;
+        self.asset_config(asset).set(&config);
     }
 
     #[endpoint]
@@ -42,18 +105,39 @@ pub trait LendingPool:
 
         self.account_positions()
             .insert(nft_token_payment.token_nonce);
+        self.enter_market_event(&caller, nft_token_payment.token_nonce);
 
         nft_token_payment.token_nonce
     }
 
+    /// Closes an account without debt. Any collateral left is withdrawn to the caller.
+    #[payable("*")]
     #[endpoint]
     fn exit_market(&self) {
-        let (_nft_account_token_id, nft_account_nonce, nft_account_amount) =
+        let (nft_account_token_id, nft_account_nonce, nft_account_amount) =
             self.call_value().single_esdt().into_tuple();
+        let initial_caller = self.blockchain().get_caller();
+
+        self.lending_account_in_the_market(nft_account_nonce);
+        self.lending_account_token_valid(nft_account_token_id);
+        require!(
+            self.borrow_positions(nft_account_nonce).is_empty(),
+            "account has outstanding debt"
+        );
+
+        let mut remaining_collaterals = ManagedVec::<Self::Api, TokenIdentifier>::new();
+        for token_id in self.deposit_positions(nft_account_nonce).keys() {
+            remaining_collaterals.push(token_id);
+        }
+        for token_id in &remaining_collaterals {
+            let amount = self.get_collateral_amount_for_token(nft_account_nonce, token_id.clone());
+            self.withdraw_collateral(nft_account_nonce, &initial_caller, token_id, amount);
+        }
 
         self.account_token()
             .nft_burn(nft_account_nonce, &nft_account_amount);
         self.account_positions().swap_remove(&nft_account_nonce);
+        self.exit_market_event(&initial_caller, nft_account_nonce);
     }
 
     #[payable("*")]
@@ -62,34 +146,200 @@ pub trait LendingPool:
         let [nft_account_token, collateral_payment] = self.call_value().multi_esdt();
         let (nft_account_token_id, nft_account_nonce, nft_account_amount) =
             nft_account_token.into_tuple();
+        let initial_caller = self.blockchain().get_caller();
+
+        self.lending_account_token_valid(nft_account_token_id.clone());
+        if self.deposit_collateral(nft_account_nonce, &initial_caller, collateral_payment, true) {
+            self.require_healthy_account(nft_account_nonce);
+        }
+
+        // Return NFT to owner
+        self.send().direct_esdt(
+            &initial_caller,
+            &nft_account_token_id,
+            nft_account_nonce,
+            &nft_account_amount,
+        );
+    }
+
+    /// Adds several collaterals at once: the account token followed by one payment per asset.
+    #[payable("*")]
+    #[endpoint(addCollateralMulti)]
+    fn add_collateral_multi(&self) {
+        let (nft_account_token, collateral_payments) = self.split_account_token_payment();
+        let initial_caller = self.blockchain().get_caller();
+
+        let mut requires_health_check = false;
+        for collateral_payment in &collateral_payments {
+            requires_health_check |= self.deposit_collateral(
+                nft_account_token.token_nonce,
+                &initial_caller,
+                collateral_payment,
+                true,
+            );
+        }
+        if requires_health_check {
+            self.require_healthy_account(nft_account_token.token_nonce);
+        }
+
+        // Return NFT to owner
+        self.send().direct_esdt(
+            &initial_caller,
+            &nft_account_token.token_identifier,
+            nft_account_token.token_nonce,
+            &nft_account_token.amount,
+        );
+    }
+
+    /// Adds collateral to any account in the market, without holding its account token.
+    /// A new position opened this way is not used as collateral until the owner enables it.
+    #[payable("*")]
+    #[endpoint(addCollateralOnBehalf)]
+    fn add_collateral_on_behalf(&self, account_nonce: u64) {
+        let collateral_payment = self.call_value().single_esdt();
+        let initial_caller = self.blockchain().get_caller();
+
+        // the deposit only opens positions that are not collateral, and adds to existing ones,
+        // so it never takes the account out of its e-mode category nor lowers its health
+        self.deposit_collateral(account_nonce, &initial_caller, collateral_payment, false);
+    }
+
+    /// `use_new_position_as_collateral` is the collateral flag of a position opened by this
+    /// deposit. Existing positions keep theirs. Returns whether the deposit took the account
+    /// out of its e-mode category, in which case the caller has to check its health once all
+    /// its deposits are made.
+    fn deposit_collateral(
+        &self,
+        account_nonce: u64,
+        caller: &ManagedAddress,
+        collateral_payment: EsdtTokenPayment,
+        use_new_position_as_collateral: bool,
+    ) -> bool {
         let (collateral_token_id, collateral_nonce, collateral_amount) =
             collateral_payment.into_tuple();
         let pool_address = self.get_pool_address(&collateral_token_id);
-        let initial_caller = self.blockchain().get_caller();
 
         self.require_asset_supported(&collateral_token_id);
-        self.lending_account_in_the_market(nft_account_nonce);
-        self.lending_account_token_valid(nft_account_token_id.clone());
+        self.lending_account_in_the_market(account_nonce);
         self.require_amount_greater_than_zero(&collateral_amount);
-        self.require_non_zero_address(&initial_caller);
-
-        let initial_or_new_deposit_position = self.get_existing_or_new_deposit_position_for_token(
-            nft_account_nonce,
-            collateral_token_id.clone(),
+        self.require_non_zero_address(caller);
+        self.require_supply_cap_not_reached(
+            &collateral_token_id,
+            &pool_address,
+            &collateral_amount,
         );
 
+        let is_new_position = !self
+            .deposit_positions(account_nonce)
+            .contains_key(&collateral_token_id);
+        let mut initial_or_new_deposit_position = self
+            .get_existing_or_new_deposit_position_for_token(
+                account_nonce,
+                collateral_token_id.clone(),
+            );
+        if is_new_position {
+            initial_or_new_deposit_position.is_collateral = use_new_position_as_collateral;
+        }
+        if initial_or_new_deposit_position.is_collateral {
+            self.require_isolation_compatible(account_nonce, &collateral_token_id);
+        }
+
         let return_deposit_position = self
-            .liquidity_pool_proxy(pool_address)
+            .liquidity_pool_proxy(pool_address.clone())
             .add_collateral(initial_or_new_deposit_position)
             .with_esdt_transfer((
                 collateral_token_id.clone(),
                 collateral_nonce,
-                collateral_amount,
+                collateral_amount.clone(),
             ))
             .execute_on_dest_context();
 
-        self.deposit_positions(nft_account_nonce)
-            .insert(collateral_token_id, return_deposit_position);
+        let (supply_index, borrow_index) = self.get_market_indexes(&pool_address);
+        self.add_collateral_event(
+            caller,
+            account_nonce,
+            &collateral_token_id,
+            &collateral_amount,
+            &supply_index,
+            &borrow_index,
+            &return_deposit_position,
+        );
+        let is_collateral = return_deposit_position.is_collateral;
+        self.deposit_positions(account_nonce)
+            .insert(collateral_token_id.clone(), return_deposit_position);
+
+        is_collateral && self.is_outside_e_mode_category(account_nonce, &collateral_token_id)
+    }
+
+    /// Selects the e-mode category of the account, 0 leaving e-mode. The category
+    /// parameters apply while all of the account's collaterals and debts belong to it.
+    #[payable("*")]
+    #[endpoint(setAccountEModeCategory)]
+    fn set_account_e_mode_category(&self, category_id: u8) {
+        let (nft_account_token_id, nft_account_nonce, nft_account_amount) =
+            self.call_value().single_esdt().into_tuple();
+        let initial_caller = self.blockchain().get_caller();
+
+        self.lending_account_in_the_market(nft_account_nonce);
+        self.lending_account_token_valid(nft_account_token_id.clone());
+        self.require_e_mode_category_exists(category_id);
+
+        self.account_e_mode_category(nft_account_nonce)
+            .set(category_id);
+        self.require_healthy_account(nft_account_nonce);
+        self.update_account_e_mode_category_event(nft_account_nonce, category_id);
+
+        // Return NFT to owner
+        self.send().direct_esdt(
+            &initial_caller,
+            &nft_account_token_id,
+            nft_account_nonce,
+            &nft_account_amount,
+        );
+    }
+
+    /// Lets a supply-only deposit count towards the borrowing power of the account again.
+    #[payable("*")]
+    #[endpoint(enableAsCollateral)]
+    fn enable_as_collateral(&self, token_id: TokenIdentifier) {
+        self.set_collateral_usage(token_id, true);
+    }
+
+    /// Keeps a deposit out of the borrowing power of the account, and out of reach of
+    /// liquidations. The account has to stay healthy without it.
+    #[payable("*")]
+    #[endpoint(disableAsCollateral)]
+    fn disable_as_collateral(&self, token_id: TokenIdentifier) {
+        self.set_collateral_usage(token_id, false);
+    }
+
+    fn set_collateral_usage(&self, token_id: TokenIdentifier, is_collateral: bool) {
+        let (nft_account_token_id, nft_account_nonce, nft_account_amount) =
+            self.call_value().single_esdt().into_tuple();
+        let initial_caller = self.blockchain().get_caller();
+
+        self.lending_account_in_the_market(nft_account_nonce);
+        self.lending_account_token_valid(nft_account_token_id.clone());
+
+        let mut deposit_positions = self.deposit_positions(nft_account_nonce);
+        let mut deposit_position = match deposit_positions.get(&token_id) {
+            Some(dp) => dp,
+            None => sc_panic!("Tokens {} are not available for this account", token_id),
+        };
+        if is_collateral {
+            require!(
+                self.asset_config(&token_id).get().can_be_collateral,
+                "asset can not be used as collateral"
+            );
+            self.require_isolation_compatible(nft_account_nonce, &token_id);
+        }
+        deposit_position.is_collateral = is_collateral;
+        deposit_positions.insert(token_id.clone(), deposit_position);
+
+        if !is_collateral || self.is_outside_e_mode_category(nft_account_nonce, &token_id) {
+            self.require_healthy_account(nft_account_nonce);
+        }
+        self.update_collateral_usage_event(nft_account_nonce, &token_id, is_collateral);
 
         // Return NFT to owner
         self.send().direct_esdt(
@@ -106,45 +356,98 @@ pub trait LendingPool:
         let (nft_account_token_id, nft_account_nonce, nft_account_amount) =
             self.call_value().single_esdt().into_tuple();
         let initial_caller = self.blockchain().get_caller();
+
+        self.lending_account_token_valid(nft_account_token_id.clone());
+        self.withdraw_collateral(
+            nft_account_nonce,
+            &initial_caller,
+            withdraw_token_id,
+            amount,
+        );
+        self.require_healthy_account(nft_account_nonce);
+
+        // Return NFT to owner
+        self.send().direct_esdt(
+            &initial_caller,
+            &nft_account_token_id,
+            nft_account_nonce,
+            &nft_account_amount,
+        );
+    }
+
+    /// Withdraws several collaterals at once, with a single health check at the end.
+    #[payable("*")]
+    #[endpoint(removeCollateralMulti)]
+    fn remove_collateral_multi(
+        &self,
+        withdrawals: MultiValueEncoded<MultiValue2<TokenIdentifier, BigUint>>,
+    ) {
+        let (nft_account_token_id, nft_account_nonce, nft_account_amount) =
+            self.call_value().single_esdt().into_tuple();
+        let initial_caller = self.blockchain().get_caller();
+
+        self.lending_account_token_valid(nft_account_token_id.clone());
+        require!(!withdrawals.is_empty(), "no assets to withdraw");
+        for withdrawal in withdrawals {
+            let (withdraw_token_id, amount) = withdrawal.into_tuple();
+            self.withdraw_collateral(
+                nft_account_nonce,
+                &initial_caller,
+                withdraw_token_id,
+                amount,
+            );
+        }
+        self.require_healthy_account(nft_account_nonce);
+
+        // Return NFT to owner
+        self.send().direct_esdt(
+            &initial_caller,
+            &nft_account_token_id,
+            nft_account_nonce,
+            &nft_account_amount,
+        );
+    }
+
+    fn withdraw_collateral(
+        &self,
+        account_nonce: u64,
+        recipient: &ManagedAddress,
+        withdraw_token_id: TokenIdentifier,
+        amount: BigUint,
+    ) {
         let pool_address = self.get_pool_address(&withdraw_token_id);
-        // let mut merged_deposits;
 
         self.require_asset_supported(&withdraw_token_id);
-        self.lending_account_in_the_market(nft_account_nonce);
-        self.lending_account_token_valid(nft_account_token_id.clone());
+        self.lending_account_in_the_market(account_nonce);
         self.require_amount_greater_than_zero(&amount);
-        self.require_non_zero_address(&initial_caller);
+        self.require_non_zero_address(recipient);
         require!(
             amount
-                > self.get_collateral_amount_for_token(
-                    nft_account_nonce,
-                    nft_account_token_id.clone()
-                ),
+                <= self.get_collateral_amount_for_token(account_nonce, withdraw_token_id.clone()),
             "Not enough tokens deposited for this account!"
         );
 
         match self
-            .deposit_positions(nft_account_nonce)
+            .deposit_positions(account_nonce)
             .get(&withdraw_token_id)
         {
             Some(dp) => {
                 let deposit_position: DepositPosition<<Self as ContractBase>::Api> = self
-                    .liquidity_pool_proxy(pool_address)
-                    .remove_collateral(&initial_caller, amount, dp)
+                    .liquidity_pool_proxy(pool_address.clone())
+                    .remove_collateral(recipient, &amount, dp)
                     .execute_on_dest_context();
 
-                if deposit_position.amount != 0 {
-                    self.deposit_positions(nft_account_nonce)
-                        .insert(withdraw_token_id, deposit_position);
-                }
-
-                // Return NFT to owner
-                self.send().direct_esdt(
-                    &initial_caller,
-                    &nft_account_token_id,
-                    nft_account_nonce,
-                    &nft_account_amount,
+                let (supply_index, borrow_index) = self.get_market_indexes(&pool_address);
+                self.remove_collateral_event(
+                    recipient,
+                    account_nonce,
+                    &withdraw_token_id,
+                    &amount,
+                    &supply_index,
+                    &borrow_index,
+                    &deposit_position,
                 );
+                self.store_deposit_position(account_nonce, deposit_position);
             }
             None => panic!(
                 "Tokens {} are not available for this account",
@@ -159,42 +462,67 @@ pub trait LendingPool:
         let (nft_account_token_id, nft_account_nonce, nft_account_amount) =
             self.call_value().single_esdt().into_tuple();
         let initial_caller = self.blockchain().get_caller();
-        let borrow_token_pool_address = self.get_pool_address(&asset_to_borrow);
-        let loan_to_value = self.get_loan_to_value_exists_and_non_zero(&asset_to_borrow);
 
-        self.require_asset_supported(&asset_to_borrow);
-        self.lending_account_in_the_market(nft_account_nonce);
         self.lending_account_token_valid(nft_account_token_id.clone());
-        self.require_amount_greater_than_zero(&amount);
-        self.require_non_zero_address(&initial_caller);
+        self.borrow_for_account(nft_account_nonce, &initial_caller, asset_to_borrow, amount);
+        self.require_healthy_account(nft_account_nonce);
 
-        self.update_collateral_with_interest(nft_account_nonce);
-        self.update_borrows_with_debt(nft_account_nonce);
+        // Return NFT to owner
+        self.send().direct_esdt(
+            &initial_caller,
+            &nft_account_token_id,
+            nft_account_nonce,
+            &nft_account_amount,
+        );
+    }
 
-        let collateral_in_dollars = self.get_total_collateral_in_dollars(nft_account_nonce);
-        let borrowed_amount_in_dollars = self.get_total_borrow_in_dollars(nft_account_nonce);
-        let amount_to_borrow_in_dollars =
-            amount.clone() * self.get_token_price_data(asset_to_borrow.clone()).price;
+    /// Borrows several assets at once, with a single health check at the end.
+    #[payable("*")]
+    #[endpoint(borrowMulti)]
+    fn borrow_multi(&self, borrows: MultiValueEncoded<MultiValue2<TokenIdentifier, BigUint>>) {
+        let (nft_account_token_id, nft_account_nonce, nft_account_amount) =
+            self.call_value().single_esdt().into_tuple();
+        let initial_caller = self.blockchain().get_caller();
 
-        require!(
-            collateral_in_dollars * loan_to_value
-                > (borrowed_amount_in_dollars + amount_to_borrow_in_dollars),
-            "Not enough collateral available for this loan!"
-        );
+        self.lending_account_token_valid(nft_account_token_id.clone());
+        require!(!borrows.is_empty(), "no assets to borrow");
+        for borrow in borrows {
+            let (asset_to_borrow, amount) = borrow.into_tuple();
+            self.borrow_for_account(nft_account_nonce, &initial_caller, asset_to_borrow, amount);
+        }
+        self.require_healthy_account(nft_account_nonce);
 
-        let initial_borrow_position = self.get_existing_or_new_borrow_position_for_token(
+        // Return NFT to owner
+        self.send().direct_esdt(
+            &initial_caller,
+            &nft_account_token_id,
             nft_account_nonce,
-            asset_to_borrow.clone(),
+            &nft_account_amount,
         );
+    }
 
-        let borrow_position = self
-            .liquidity_pool_proxy(borrow_token_pool_address)
-            .borrow(&initial_caller, amount, initial_borrow_position)
-            .execute_on_dest_context();
+    /// Sets how much of `asset` the `delegate` may borrow against the sent account's collateral.
+    /// Setting it to zero revokes the delegation.
+    #[payable("*")]
+    #[endpoint(approveBorrowDelegation)]
+    fn approve_borrow_delegation(
+        &self,
+        delegate: ManagedAddress,
+        asset: TokenIdentifier,
+        amount: BigUint,
+    ) {
+        let (nft_account_token_id, nft_account_nonce, nft_account_amount) =
+            self.call_value().single_esdt().into_tuple();
+        let initial_caller = self.blockchain().get_caller();
 
-        // TODO: Remove existing position
-        self.borrow_positions(nft_account_nonce)
-            .insert(asset_to_borrow, borrow_position);
+        self.lending_account_in_the_market(nft_account_nonce);
+        self.lending_account_token_valid(nft_account_token_id.clone());
+        self.require_non_zero_address(&delegate);
+        self.require_asset_supported(&asset);
+
+        self.borrow_allowance(nft_account_nonce, &delegate, &asset)
+            .set(&amount);
+        self.update_borrow_allowance_event(nft_account_nonce, &delegate, &asset, &amount);
 
         // Return NFT to owner
         self.send().direct_esdt(
@@ -205,47 +533,169 @@ pub trait LendingPool:
         );
     }
 
+    /// Borrows against the collateral of a delegating account, debiting the caller's allowance.
+    /// The debt is recorded on the delegating account and the tokens go to the caller.
+    #[endpoint(borrowOnBehalf)]
+    fn borrow_on_behalf(
+        &self,
+        account_nonce: u64,
+        asset_to_borrow: TokenIdentifier,
+        amount: BigUint,
+    ) {
+        let initial_caller = self.blockchain().get_caller();
+        let allowance_mapper =
+            self.borrow_allowance(account_nonce, &initial_caller, &asset_to_borrow);
+        let allowance = allowance_mapper.get();
+
+        require!(amount <= allowance, "borrow allowance exceeded");
+        allowance_mapper.set(&(allowance - &amount));
+
+        self.borrow_for_account(account_nonce, &initial_caller, asset_to_borrow, amount);
+        self.require_healthy_account(account_nonce);
+    }
+
+    fn borrow_for_account(
+        &self,
+        account_nonce: u64,
+        recipient: &ManagedAddress,
+        asset_to_borrow: TokenIdentifier,
+        amount: BigUint,
+    ) {
+        let borrow_token_pool_address = self.get_pool_address(&asset_to_borrow);
+        require!(
+            self.asset_config(&asset_to_borrow).get().can_borrow,
+            "asset can not be borrowed"
+        );
+
+        self.require_asset_supported(&asset_to_borrow);
+        self.lending_account_in_the_market(account_nonce);
+        self.require_amount_greater_than_zero(&amount);
+        self.require_non_zero_address(recipient);
+        self.require_borrow_cap_not_reached(&asset_to_borrow, &borrow_token_pool_address, &amount);
+        self.require_siloed_borrowing_respected(account_nonce, &asset_to_borrow);
+        self.add_isolated_debt(account_nonce, &asset_to_borrow, &amount);
+
+        let initial_borrow_position = self
+            .get_existing_or_new_borrow_position_for_token(account_nonce, asset_to_borrow.clone());
+
+        let borrow_position = self
+            .liquidity_pool_proxy(borrow_token_pool_address.clone())
+            .borrow(recipient, &amount, initial_borrow_position)
+            .execute_on_dest_context();
+
+        let (supply_index, borrow_index) = self.get_market_indexes(&borrow_token_pool_address);
+        self.borrow_event(
+            recipient,
+            account_nonce,
+            &asset_to_borrow,
+            &amount,
+            &supply_index,
+            &borrow_index,
+            &borrow_position,
+        );
+        self.borrow_positions(account_nonce)
+            .insert(asset_to_borrow, borrow_position);
+    }
+
     #[payable("*")]
     #[endpoint]
     fn repay(&self) {
         let [nft_account_token, payment_repay] = self.call_value().multi_esdt();
         let (nft_account_token_id, nft_account_nonce, nft_account_amount) =
             nft_account_token.into_tuple();
-        let (repay_token_id, repay_nonce, repay_amount) = payment_repay.into_tuple();
         let initial_caller = self.blockchain().get_caller();
-        let asset_address = self.get_pool_address(&repay_token_id);
 
-        self.lending_account_in_the_market(nft_account_nonce);
         self.lending_account_token_valid(nft_account_token_id.clone());
-        self.lending_account_token_valid(repay_token_id.clone());
+        self.repay_borrow_position(nft_account_nonce, &initial_caller, payment_repay);
+
+        // Return NFT to owner
+        self.send().direct_esdt(
+            &initial_caller,
+            &nft_account_token_id,
+            nft_account_nonce,
+            &nft_account_amount,
+        );
+    }
+
+    /// Repays several debts at once: the account token followed by one payment per asset.
+    #[payable("*")]
+    #[endpoint(repayMulti)]
+    fn repay_multi(&self) {
+        let (nft_account_token, repay_payments) = self.split_account_token_payment();
+        let initial_caller = self.blockchain().get_caller();
+
+        for payment_repay in &repay_payments {
+            self.repay_borrow_position(
+                nft_account_token.token_nonce,
+                &initial_caller,
+                payment_repay,
+            );
+        }
+
+        // Return NFT to owner
+        self.send().direct_esdt(
+            &initial_caller,
+            &nft_account_token.token_identifier,
+            nft_account_token.token_nonce,
+            &nft_account_token.amount,
+        );
+    }
+
+    /// Repays debt of any account in the market, without holding its account token.
+    /// Any overpayment is refunded to the caller.
+    #[payable("*")]
+    #[endpoint(repayOnBehalf)]
+    fn repay_on_behalf(&self, account_nonce: u64) {
+        let payment_repay = self.call_value().single_esdt();
+        let initial_caller = self.blockchain().get_caller();
+
+        self.repay_borrow_position(account_nonce, &initial_caller, payment_repay);
+    }
+
+    fn repay_borrow_position(
+        &self,
+        account_nonce: u64,
+        payer: &ManagedAddress,
+        payment_repay: EsdtTokenPayment,
+    ) {
+        let (repay_token_id, repay_nonce, repay_amount) = payment_repay.into_tuple();
+        let asset_address = self.get_pool_address(&repay_token_id);
+
+        self.lending_account_in_the_market(account_nonce);
         self.require_amount_greater_than_zero(&repay_amount);
-        self.require_non_zero_address(&initial_caller);
+        self.require_non_zero_address(payer);
         self.require_asset_supported(&repay_token_id);
 
-        match self
-            .borrow_positions(nft_account_nonce)
-            .get(&repay_token_id)
-        {
+        match self.borrow_positions(account_nonce).get(&repay_token_id) {
             Some(bp) => {
                 let borrow_position: BorrowPosition<Self::Api> = self
-                    .liquidity_pool_proxy(asset_address)
-                    .repay(&initial_caller, bp)
-                    .with_esdt_transfer((repay_token_id.clone(), repay_nonce, repay_amount))
+                    .liquidity_pool_proxy(asset_address.clone())
+                    .repay(payer, bp.clone())
+                    .with_esdt_transfer((repay_token_id.clone(), repay_nonce, repay_amount.clone()))
                     .execute_on_dest_context();
 
-                // TOdo Remove old BorrowPosition
-                if borrow_position.amount != 0 {
-                    self.borrow_positions(nft_account_nonce)
-                        .insert(repay_token_id, borrow_position);
-                }
+                self.remove_isolated_debt(account_nonce, &repay_token_id, &borrow_position.amount);
 
-                // Return NFT to owner
-                self.send().direct_esdt(
-                    &initial_caller,
-                    &nft_account_token_id,
-                    nft_account_nonce,
-                    &nft_account_amount,
+                let applied_amount = if borrow_position.amount == 0 {
+                    let accrued_debt: BigUint = self
+                        .liquidity_pool_proxy(asset_address.clone())
+                        .get_debt_interest(&bp.amount, &bp.initial_borrow_index)
+                        .execute_on_dest_context();
+                    &bp.amount + &accrued_debt
+                } else {
+                    repay_amount
+                };
+                let (supply_index, borrow_index) = self.get_market_indexes(&asset_address);
+                self.repay_event(
+                    payer,
+                    account_nonce,
+                    &repay_token_id,
+                    &applied_amount,
+                    &supply_index,
+                    &borrow_index,
+                    &borrow_position,
                 );
+                self.store_borrow_position(account_nonce, borrow_position);
             }
             None => panic!(
                 "Borrowed tokens {} are not available for this account",
@@ -254,6 +704,10 @@ pub trait LendingPool:
         };
     }
 
+    /// The health factor weighs each collateral by the liquidation threshold in effect when the
+    /// liquidation executes. `liquidation_threshold` is the share of the debt the liquidator
+    /// has to cover, at most 50%. The payment repays the liquidatee's debt in that token,
+    /// and the collateral seized is worth the amount repaid plus the liquidation bonus.
     #[payable("*")]
     #[endpoint(liquidate)]
     fn liquidate(
@@ -283,16 +737,19 @@ pub trait LendingPool:
             MAX_THRESHOLD_ERROR_MSG
         );
 
-        let liq_bonus = self.get_liquidation_bonus_non_zero(&liquidator_asset_token_id);
-        let total_collateral_in_dollars =
-            self.get_total_collateral_in_dollars(liquidatee_account_nonce);
+        self.update_collateral_with_interest(liquidatee_account_nonce);
+        self.update_borrows_with_debt(liquidatee_account_nonce);
+
+        let e_mode_category = self.get_active_e_mode_category(liquidatee_account_nonce);
+        let liq_bonus = match &e_mode_category {
+            Some(category) => category.liquidation_bonus.clone(),
+            None => self.get_liquidation_bonus_non_zero(&liquidator_asset_token_id),
+        };
+        let weighted_collateral_in_dollars =
+            self.get_weighted_collateral_in_dollars(liquidatee_account_nonce, &e_mode_category);
         let borrowed_value_in_dollars = self.get_total_borrow_in_dollars(liquidatee_account_nonce);
 
-        let health_factor = self.compute_health_factor(
-            &total_collateral_in_dollars,
-            &borrowed_value_in_dollars,
-            &liquidation_threshold,
-        );
+        let health_factor = weighted_collateral_in_dollars / &borrowed_value_in_dollars;
         require!(health_factor < BP, "health not low enough for liquidation");
 
         let liquidator_asset_data = self.get_token_price_data(liquidator_asset_token_id);
@@ -305,9 +762,34 @@ pub trait LendingPool:
             "insufficient funds for liquidation"
         );
 
+        let asset_address = self.get_pool_address(&token_to_liquidate);
+
+        // the payment repays the liquidatee's debt, any overpayment is refunded
+        let borrow_position = self
+            .borrow_positions(liquidatee_account_nonce)
+            .get(&token_to_liquidate);
+        require!(
+            borrow_position.is_some(),
+            "Liquidatee user has no debt in the liquidation token"
+        );
+        let borrow_position = borrow_position.unwrap();
+        let debt_before_repay = borrow_position.amount.clone();
+        let borrow_position: BorrowPosition<Self::Api> = self
+            .liquidity_pool_proxy(asset_address.clone())
+            .repay(&initial_caller, borrow_position)
+            .with_esdt_transfer((token_to_liquidate.clone(), 0, liquidator_asset_amount))
+            .execute_on_dest_context();
+        let repaid_amount = &debt_before_repay - &borrow_position.amount;
+
+        self.remove_isolated_debt(
+            liquidatee_account_nonce,
+            &token_to_liquidate,
+            &borrow_position.amount,
+        );
+        self.store_borrow_position(liquidatee_account_nonce, borrow_position);
+
         // amount_liquidated (1 + liq_bonus)
-        let amount_to_return_to_liquidator_in_dollars =
-            (liquidator_asset_amount * (&bp + &liq_bonus)) / bp;
+        let amount_to_return_to_liquidator_in_dollars = (&repaid_amount * &(&bp + &liq_bonus)) / bp;
 
         // Go through all DepositPositions and send amount_to_return_in_dollars to Liquidator
         let amount_to_send = self.compute_amount_in_tokens(
@@ -316,11 +798,45 @@ pub trait LendingPool:
             amount_to_return_to_liquidator_in_dollars,
         );
 
-        let asset_address = self.get_pool_address(&token_to_liquidate);
+        // the seized collateral leaves the liquidatee's position
+        let deposit_position = self
+            .deposit_positions(liquidatee_account_nonce)
+            .get(&token_to_liquidate);
+        require!(
+            deposit_position.is_some(),
+            "Liquidatee user has no collateral in the liquidation token"
+        );
+        let deposit_position = deposit_position.unwrap();
+        require!(
+            amount_to_send <= deposit_position.amount,
+            "Liquidatee user doesn't have enough collateral"
+        );
+        let deposit_position: DepositPosition<Self::Api> = self
+            .liquidity_pool_proxy(asset_address.clone())
+            .remove_collateral(&initial_caller, &amount_to_send, deposit_position)
+            .execute_on_dest_context();
 
-        self.liquidity_pool_proxy(asset_address)
-            .send_tokens(&initial_caller, &amount_to_send)
-            .execute_on_dest_context::<IgnoreValue>();
+        let (supply_index, borrow_index) = self.get_market_indexes(&asset_address);
+        self.liquidate_event(
+            &initial_caller,
+            liquidatee_account_nonce,
+            &token_to_liquidate,
+            &repaid_amount,
+            &amount_to_send,
+            &supply_index,
+            &borrow_index,
+            &deposit_position,
+        );
+        self.store_deposit_position(liquidatee_account_nonce, deposit_position);
+    }
+
+    /// Brings all positions of an account up to date with the accrued interest and debt.
+    #[endpoint(accrueAccount)]
+    fn accrue_account(&self, account_nonce: u64) {
+        self.lending_account_in_the_market(account_nonce);
+
+        self.update_collateral_with_interest(account_nonce);
+        self.update_borrows_with_debt(account_nonce);
     }
 
     #[endpoint(updateCollateralWithInterest)]
@@ -329,9 +845,13 @@ pub trait LendingPool:
 
         for dp in deposit_positions.values() {
             let asset_address = self.get_pool_address(&dp.token_id);
-            self.liquidity_pool_proxy(asset_address)
+            let updated_position: DepositPosition<Self::Api> = self
+                .liquidity_pool_proxy(asset_address)
                 .update_collateral_with_interest(dp)
-                .execute_on_dest_context::<IgnoreValue>();
+                .execute_on_dest_context();
+
+            self.deposit_positions(account_position)
+                .insert(updated_position.token_id.clone(), updated_position);
         }
     }
 
@@ -341,12 +861,262 @@ pub trait LendingPool:
 
         for bp in borrow_positions.values() {
             let asset_address = self.get_pool_address(&bp.token_id);
-            self.liquidity_pool_proxy(asset_address)
+            let updated_position: BorrowPosition<Self::Api> = self
+                .liquidity_pool_proxy(asset_address)
                 .update_borrows_with_debt(bp)
-                .execute_on_dest_context::<IgnoreValue>();
+                .execute_on_dest_context();
+
+            self.borrow_positions(account_position)
+                .insert(updated_position.token_id.clone(), updated_position);
+        }
+    }
+
+    /// Splits a multi-transfer into the leading account token and the asset payments after it.
+    fn split_account_token_payment(&self) -> (EsdtTokenPayment, ManagedVec<EsdtTokenPayment>) {
+        let mut payments = self.call_value().all_esdt_transfers().clone_value();
+        require!(
+            payments.len() > 1,
+            "account token and at least one payment expected"
+        );
+
+        let nft_account_token = payments.get(0);
+        payments.remove(0);
+        self.lending_account_token_valid(nft_account_token.token_identifier.clone());
+
+        (nft_account_token, payments)
+    }
+
+    /// Collateral outside the selected category turns the account back to the per-asset
+    /// parameters, which may leave it less healthy than before.
+    fn is_outside_e_mode_category(&self, account_nonce: u64, token_id: &TokenIdentifier) -> bool {
+        let category_id = self.account_e_mode_category(account_nonce).get();
+
+        category_id != 0 && self.asset_config(token_id).get().e_mode_category != category_id
+    }
+
+    /// A siloed asset can only be borrowed alone, and no other asset can be borrowed next to it.
+    fn require_siloed_borrowing_respected(&self, account_nonce: u64, asset: &TokenIdentifier) {
+        let is_siloed = self.asset_config(asset).get().is_siloed;
+
+        for token_id in self.borrow_positions(account_nonce).keys() {
+            if &token_id == asset {
+                continue;
+            }
+
+            require!(
+                !is_siloed && !self.asset_config(&token_id).get().is_siloed,
+                "siloed asset cannot be borrowed with other assets"
+            );
         }
     }
 
+    /// Returns the isolated asset the account uses as collateral, if any.
+    fn get_isolated_collateral(&self, account_nonce: u64) -> Option<TokenIdentifier> {
+        self.deposit_positions(account_nonce)
+            .values()
+            .find(|dp| dp.is_collateral && self.asset_config(&dp.token_id).get().is_isolated)
+            .map(|dp| dp.token_id)
+    }
+
+    fn require_isolation_compatible(
+        &self,
+        account_nonce: u64,
+        collateral_token_id: &TokenIdentifier,
+    ) {
+        let is_isolated = self.asset_config(collateral_token_id).get().is_isolated;
+
+        for dp in self.deposit_positions(account_nonce).values() {
+            if !dp.is_collateral || &dp.token_id == collateral_token_id {
+                continue;
+            }
+
+            require!(
+                !is_isolated && !self.asset_config(&dp.token_id).get().is_isolated,
+                "isolated collateral cannot be combined with other collateral"
+            );
+        }
+    }
+
+    /// Accounts in isolation mode borrow only isolation borrowable assets, and their debt
+    /// counts towards the debt ceiling of their isolated collateral.
+    fn add_isolated_debt(&self, account_nonce: u64, asset: &TokenIdentifier, amount: &BigUint) {
+        let isolated_token_id = match self.get_isolated_collateral(account_nonce) {
+            Some(token_id) => token_id,
+            None => return,
+        };
+
+        require!(
+            self.isolation_borrowable_assets().contains(asset),
+            "asset not borrowable in isolation mode"
+        );
+
+        // debt taken against a previous isolated collateral now counts towards this one
+        let mut added_units = self.to_debt_ceiling_units(asset, amount);
+        let debt_collateral = self.isolated_debt_collateral(account_nonce, asset);
+        if !debt_collateral.is_empty() && debt_collateral.get() != isolated_token_id {
+            let account_debt = self.isolated_account_debt(account_nonce, asset).get();
+            let moved_units = self.to_debt_ceiling_units(asset, &account_debt);
+            self.remove_isolated_asset_debt(&debt_collateral.get(), &moved_units);
+            added_units += moved_units;
+        }
+
+        let isolated_debt = self.isolated_asset_debt(&isolated_token_id).get() + added_units;
+        let debt_ceiling = self.asset_config(&isolated_token_id).get().debt_ceiling;
+        require!(
+            debt_ceiling == 0 || isolated_debt <= debt_ceiling,
+            "debt ceiling reached"
+        );
+
+        self.isolated_asset_debt(&isolated_token_id)
+            .set(isolated_debt);
+        debt_collateral.set(&isolated_token_id);
+        self.isolated_account_debt(account_nonce, asset)
+            .update(|account_debt| *account_debt += amount);
+    }
+
+    /// Repayments cover the interest first, so the principal still owed is at most what is
+    /// left of the borrow position. Only the principal repaid leaves the debt ceiling, of the
+    /// collateral the debt was taken against, even if the account no longer holds it.
+    fn remove_isolated_debt(
+        &self,
+        account_nonce: u64,
+        asset: &TokenIdentifier,
+        remaining_debt: &BigUint,
+    ) {
+        let debt_collateral = self.isolated_debt_collateral(account_nonce, asset);
+        if debt_collateral.is_empty() {
+            return;
+        }
+
+        let account_debt = self.isolated_account_debt(account_nonce, asset).get();
+        if &account_debt <= remaining_debt {
+            return;
+        }
+
+        let isolated_token_id = debt_collateral.get();
+        let repaid_principal = &account_debt - remaining_debt;
+        if remaining_debt == &0 {
+            self.isolated_account_debt(account_nonce, asset).clear();
+            debt_collateral.clear();
+        } else {
+            self.isolated_account_debt(account_nonce, asset)
+                .set(remaining_debt);
+        }
+
+        let repaid_units = self.to_debt_ceiling_units(asset, &repaid_principal);
+        self.remove_isolated_asset_debt(&isolated_token_id, &repaid_units);
+    }
+
+    fn remove_isolated_asset_debt(&self, isolated_token_id: &TokenIdentifier, units: &BigUint) {
+        self.isolated_asset_debt(isolated_token_id)
+            .update(|isolated_debt| {
+                // amounts are rounded down when normalised, so their sum may exceed the tally
+                if &*isolated_debt > units {
+                    *isolated_debt -= units;
+                } else {
+                    *isolated_debt = BigUint::zero();
+                }
+            });
+    }
+
+    /// Normalises an amount of `asset` to the `DEBT_CEILING_DECIMALS` decimals of debt ceilings.
+    fn to_debt_ceiling_units(&self, asset: &TokenIdentifier, amount: &BigUint) -> BigUint {
+        let decimals = self.asset_config(asset).get().decimals as u32;
+
+        amount * &BigUint::from(10u64).pow(DEBT_CEILING_DECIMALS as u32)
+            / BigUint::from(10u64).pow(decimals)
+    }
+
+    /// Collateral value of the account weighted by the liquidation threshold in effect for each
+    /// asset, or by the one of its active e-mode category, in BP precision.
+    fn get_weighted_collateral_in_dollars(
+        &self,
+        account_nonce: u64,
+        e_mode_category: &Option<EModeCategory<Self::Api>>,
+    ) -> BigUint {
+        let mut weighted_collateral_in_dollars = BigUint::zero();
+        for dp in self.deposit_positions(account_nonce).values() {
+            if !self.counts_as_collateral(&dp) {
+                continue;
+            }
+
+            let liquidation_threshold =
+                self.get_collateral_liquidation_threshold(&dp.token_id, e_mode_category);
+            let dp_data = self.get_token_price_data(dp.token_id);
+            weighted_collateral_in_dollars += dp.amount * dp_data.price * liquidation_threshold;
+        }
+
+        weighted_collateral_in_dollars
+    }
+
+    /// Requires the borrowing power of the account, its collateral weighted by each asset's
+    /// loan to value, to cover the value of its debt. Debt equal to it is allowed.
+    fn require_healthy_account(&self, account_nonce: u64) {
+        self.update_collateral_with_interest(account_nonce);
+        self.update_borrows_with_debt(account_nonce);
+
+        let borrowed_in_dollars = self.get_total_borrow_in_dollars(account_nonce);
+        if borrowed_in_dollars == 0 {
+            return;
+        }
+
+        let e_mode_category = self.get_active_e_mode_category(account_nonce);
+        let mut borrowing_power_in_dollars = BigUint::zero();
+        for dp in self.deposit_positions(account_nonce).values() {
+            if !self.counts_as_collateral(&dp) {
+                continue;
+            }
+
+            let loan_to_value = match &e_mode_category {
+                Some(category) => category.loan_to_value.clone(),
+                None => self.get_asset_risk_params(&dp.token_id).loan_to_value,
+            };
+            let dp_data = self.get_token_price_data(dp.token_id);
+            borrowing_power_in_dollars += dp.amount * dp_data.price * loan_to_value / BP;
+        }
+
+        require!(
+            borrowing_power_in_dollars >= borrowed_in_dollars,
+            "Not enough collateral available for this loan!"
+        );
+    }
+
+    /// Stores a deposit position, or drops it once fully withdrawn.
+    fn store_deposit_position(&self, account_nonce: u64, position: DepositPosition<Self::Api>) {
+        if position.amount != 0 {
+            self.deposit_positions(account_nonce)
+                .insert(position.token_id.clone(), position);
+        } else {
+            self.deposit_positions(account_nonce)
+                .remove(&position.token_id);
+        }
+    }
+
+    /// Stores a borrow position, or drops it once fully repaid.
+    fn store_borrow_position(&self, account_nonce: u64, position: BorrowPosition<Self::Api>) {
+        if position.amount != 0 {
+            self.borrow_positions(account_nonce)
+                .insert(position.token_id.clone(), position);
+        } else {
+            self.borrow_positions(account_nonce)
+                .remove(&position.token_id);
+        }
+    }
+
+    /// Current (supply index, borrow index) of a market, as carried by the account events.
+    fn get_market_indexes(&self, pool_address: &ManagedAddress) -> (BigUint, BigUint) {
+        let supply_index: BigUint = self
+            .liquidity_pool_proxy(pool_address.clone())
+            .supply_index()
+            .execute_on_dest_context();
+        let borrow_index: BigUint = self
+            .liquidity_pool_proxy(pool_address.clone())
+            .borrow_index()
+            .execute_on_dest_context();
+
+        (supply_index, borrow_index)
+    }
+
     fn caller_from_option_or_sender(
         &self,
         caller: OptionalValue<ManagedAddress>,
@@ -4,59 +4,13 @@ use common_structs::BP;
 
 #[multiversx_sc::module]
 pub trait LendingMathModule {
-    fn compute_borrow_rate(
-        &self,
-        r_base: &BigUint,
-        r_slope1: &BigUint,
-        r_slope2: &BigUint,
-        u_optimal: &BigUint,
-        u_current: &BigUint,
-    ) -> BigUint {
-        if u_current <= u_optimal {
-            let utilisation_ratio = &(u_current * r_slope1) / u_optimal;
-            r_base + &utilisation_ratio
-        } else {
-            let bp = BigUint::from(BP);
-
-            let denominator = &bp - u_optimal;
-            let numerator = &(u_current - u_optimal) * r_slope2;
-            (r_base + r_slope1) + numerator / denominator
-        }
-    }
-
-    fn compute_deposit_rate(
-        &self,
-        u_current: &BigUint,
-        borrow_rate: &BigUint,
-        reserve_factor: &BigUint,
-    ) -> BigUint {
-        let bp = BigUint::from(BP);
-
-        let loan_ratio = u_current * borrow_rate;
-        let deposit_rate = &(u_current * &loan_ratio) * &(&bp - reserve_factor);
-
-        deposit_rate / (&bp * &bp * bp)
-    }
-
-    fn compute_capital_utilisation(
-        &self,
-        borrowed_amount: &BigUint,
-        total_reserves: &BigUint,
-    ) -> BigUint {
-        if *total_reserves == BigUint::zero() {
-            total_reserves.clone()
-        } else {
-            &(borrowed_amount * BP) / total_reserves
-        }
-    }
-
     fn compute_withdrawal_amount(
         &self,
         amount: &BigUint,
         current_supply_index: &BigUint,
         initial_supply_index: &BigUint,
     ) -> BigUint {
-        let interest = (current_supply_index - initial_supply_index) * amount / BP;
+        let interest = self.compute_interest(amount, current_supply_index, initial_supply_index);
 
         amount + &interest
     }
@@ -67,7 +21,7 @@ pub trait LendingMathModule {
         current_supply_index: &BigUint,
         initial_supply_index: &BigUint,
     ) -> BigUint {
-        (current_supply_index - initial_supply_index) * amount / BP
+        (current_supply_index - initial_supply_index) * amount / initial_supply_index
     }
 
     fn compute_borrowable_amount(
@@ -79,16 +33,27 @@ pub trait LendingMathModule {
         ((total_collateral * loan_to_value) / BP) / BigUint::from(10u64).pow(decimals as u32)
     }
 
-    fn compute_health_factor(
+    /// Value at `timestamp` on the line from `start_value` at `start_timestamp` to
+    /// `end_value` at `end_timestamp`, staying at `end_value` afterwards
+    fn linear_interpolation(
         &self,
-        collateral_value_in_dollars: &BigUint,
-        borrowed_value_in_dollars: &BigUint,
-        liquidation_threshold: &BigUint,
+        start_value: &BigUint,
+        end_value: &BigUint,
+        start_timestamp: u64,
+        end_timestamp: u64,
+        timestamp: u64,
     ) -> BigUint {
-        let allowed_collateral_in_dollars = collateral_value_in_dollars * liquidation_threshold;
-        let health_factor = &allowed_collateral_in_dollars / borrowed_value_in_dollars;
+        if timestamp >= end_timestamp {
+            return end_value.clone();
+        }
 
-        health_factor / BP
+        let elapsed = BigUint::from(timestamp - start_timestamp);
+        let duration = BigUint::from(end_timestamp - start_timestamp);
+        if end_value >= start_value {
+            start_value + &((end_value - start_value) * elapsed / duration)
+        } else {
+            start_value - &((start_value - end_value) * elapsed / duration)
+        }
     }
 
     fn rule_of_three(&self, value: &BigUint, part: &BigUint, total: &BigUint) -> BigUint {
@@ -3,15 +3,32 @@
 multiversx_sc::imports!();
 multiversx_sc::derive_imports!();
 
+use super::events;
 use super::factory;
+use super::math;
 use super::proxy;
+use super::storage;
 
+use common_structs::{
+    AssetConfig, AssetRiskParams, EModeCategory, InterestRateModel, PoolParams, RiskParamsRamp,
+};
+
+use liquidity_pool::liq_storage::ProxyTrait as _;
+use liquidity_pool::liq_utils::ProxyTrait as _;
+use liquidity_pool::ProxyTrait as _;
 use price_aggregator_proxy::ProxyTrait as _;
 
 #[multiversx_sc::module]
 pub trait RouterModule:
-    proxy::ProxyModule + factory::FactoryModule + common_checks::ChecksModule
+    proxy::ProxyModule
+    + factory::FactoryModule
+    + common_checks::ChecksModule
+    + events::EventsModule
+    + storage::LendingStorageModule
+    + math::LendingMathModule
 {
+    /// `decimals` are the decimals of the token, which normalise the amounts borrowed
+    /// against isolation debt ceilings.
     #[only_owner]
     #[endpoint(createLiquidityPool)]
     fn create_liquidity_pool(
@@ -23,6 +40,7 @@ pub trait RouterModule:
         u_optimal: BigUint,
         reserve_factor: BigUint,
         liquidation_threshold: BigUint,
+        decimals: u8,
     ) -> ManagedAddress {
         require!(
             !self.pools_map().contains_key(&base_asset),
@@ -32,6 +50,14 @@ pub trait RouterModule:
             base_asset.is_valid_esdt_identifier(),
             "invalid ticker provided"
         );
+        self.require_valid_pool_config(
+            &r_base,
+            &r_slope1,
+            &r_slope2,
+            &u_optimal,
+            &reserve_factor,
+            &liquidation_threshold,
+        );
 
         let address = self.create_pool(
             base_asset.clone(),
@@ -40,11 +66,14 @@ pub trait RouterModule:
             r_slope2,
             u_optimal,
             reserve_factor,
-            liquidation_threshold,
+            liquidation_threshold.clone(),
         );
 
         self.require_non_zero_address(&address);
 
+        self.create_market_event(&base_asset, &address);
+        self.asset_config(&base_asset)
+            .set(AssetConfig::new(liquidation_threshold, decimals));
         self.pools_map().insert(base_asset, address.clone());
         self.pools_allowed().insert(address.clone());
         address
@@ -66,8 +95,25 @@ pub trait RouterModule:
             self.pools_map().contains_key(&base_asset),
             "no pool found for this asset"
         );
+        self.require_valid_pool_config(
+            &r_base,
+            &r_slope1,
+            &r_slope2,
+            &u_optimal,
+            &reserve_factor,
+            &liquidation_threshold,
+        );
 
         let pool_address = self.get_pool_address(&base_asset);
+        let mut config = self.asset_config(&base_asset).get();
+        self.require_no_risk_params_ramp_in_progress(&base_asset);
+        self.settle_risk_params_ramp(&base_asset, &mut config, false);
+        if config.loan_to_value > 0 {
+            self.require_valid_loan_to_value(&config.loan_to_value, &liquidation_threshold);
+        }
+        config.liquidation_threshold = liquidation_threshold.clone();
+        self.asset_config(&base_asset).set(&config);
+        self.update_asset_liquidation_threshold_event(&base_asset, &liquidation_threshold);
         self.upgrade_pool(
             pool_address,
             base_asset,
@@ -80,6 +126,80 @@ pub trait RouterModule:
         );
     }
 
+    /// Upgrades every pool in `pools_map` to the current template, keeping each
+    /// pool's parameters. A pool upgrade is an asynchronous call that ends the
+    /// execution, so it can't be looped over in one transaction: this call queues
+    /// every pool, upgrades the first and leaves the rest to `continuePoolsUpgrade`.
+    #[only_owner]
+    #[endpoint(upgradeAllLiquidityPools)]
+    fn upgrade_all_liquidity_pools(&self) {
+        require!(
+            self.pools_pending_upgrade().is_empty(),
+            "a pools upgrade is already in progress"
+        );
+        require!(!self.pools_map().is_empty(), "no pools to upgrade");
+
+        for base_asset in self.pools_map().keys() {
+            self.pools_pending_upgrade().push_back(base_asset);
+        }
+
+        self.upgrade_next_pending_pool();
+    }
+
+    /// Upgrades the next queued pool. Anyone can carry on an upgrade the owner
+    /// started, as it only applies the current template with the pool's own parameters.
+    #[endpoint(continuePoolsUpgrade)]
+    fn continue_pools_upgrade(&self) {
+        require!(
+            !self.pools_pending_upgrade().is_empty(),
+            "no pools upgrade in progress"
+        );
+
+        self.upgrade_next_pending_pool();
+    }
+
+    /// Accrues interest on every pool, so views and liquidations read fresh indexes.
+    #[endpoint(accrueAll)]
+    fn accrue_all(&self) {
+        for pool_address in self.pools_map().values() {
+            self.liquidity_pool_proxy(pool_address)
+                .update_interest_indexes()
+                .execute_on_dest_context::<IgnoreValue>();
+        }
+    }
+
+    /// Does nothing when no pool is queued. The upgrade call ends the execution,
+    /// so the pool leaves the queue right before it. A failed upgrade can be
+    /// retried with `upgradeLiquidityPool`.
+    fn upgrade_next_pending_pool(&self) {
+        let base_asset = match self.pools_pending_upgrade().front() {
+            Some(base_asset) => base_asset,
+            None => return,
+        };
+        let pool_address = self.get_pool_address(&base_asset);
+
+        let pool_params: PoolParams<Self::Api> = self
+            .liquidity_pool_proxy(pool_address.clone())
+            .pool_params()
+            .execute_on_dest_context();
+        let liquidation_threshold: BigUint = self
+            .liquidity_pool_proxy(pool_address.clone())
+            .liquidation_threshold()
+            .execute_on_dest_context();
+
+        self.pools_pending_upgrade().pop_front();
+        self.upgrade_pool(
+            pool_address,
+            base_asset,
+            pool_params.r_base,
+            pool_params.r_slope1,
+            pool_params.r_slope2,
+            pool_params.u_optimal,
+            pool_params.reserve_factor,
+            liquidation_threshold,
+        );
+    }
+
     #[only_owner]
     #[endpoint(setAggregator)]
     fn set_aggregator(&self, pool_asset_id: TokenIdentifier, aggregator: ManagedAddress) {
@@ -91,15 +211,470 @@ pub trait RouterModule:
     }
 
     #[only_owner]
+    #[endpoint(setPoolParams)]
+    fn set_pool_params(
+        &self,
+        pool_asset_id: TokenIdentifier,
+        r_base: BigUint,
+        r_slope1: BigUint,
+        r_slope2: BigUint,
+        u_optimal: BigUint,
+        reserve_factor: BigUint,
+    ) {
+        let pool_address = self.get_pool_address(&pool_asset_id);
+
+        self.liquidity_pool_proxy(pool_address)
+            .set_pool_params(r_base, r_slope1, r_slope2, u_optimal, reserve_factor)
+            .execute_on_dest_context::<IgnoreValue>();
+    }
+
+    #[endpoint(setLiquidationThreshold)]
+    fn set_liquidation_threshold(
+        &self,
+        pool_asset_id: TokenIdentifier,
+        liquidation_threshold: BigUint,
+    ) {
+        self.require_caller_risk_admin();
+        let pool_address = self.get_pool_address(&pool_asset_id);
+        let mut config = self.asset_config(&pool_asset_id).get();
+        self.settle_risk_params_ramp(&pool_asset_id, &mut config, false);
+        if config.loan_to_value > 0 {
+            self.require_valid_loan_to_value(&config.loan_to_value, &liquidation_threshold);
+        }
+
+        // the pool validates the threshold and keeps a copy of it
+        self.liquidity_pool_proxy(pool_address)
+            .set_liquidation_threshold(liquidation_threshold.clone())
+            .execute_on_dest_context::<IgnoreValue>();
+
+        config.liquidation_threshold = liquidation_threshold;
+        self.asset_config(&pool_asset_id).set(&config);
+        self.update_asset_liquidation_threshold_event(
+            &pool_asset_id,
+            &config.liquidation_threshold,
+        );
+    }
+
+    #[only_owner]
+    #[endpoint(setInterestRateModel)]
+    fn set_interest_rate_model(
+        &self,
+        pool_asset_id: TokenIdentifier,
+        model: InterestRateModel<Self::Api>,
+    ) {
+        let pool_address = self.get_pool_address(&pool_asset_id);
+
+        self.liquidity_pool_proxy(pool_address)
+            .set_interest_rate_model(model)
+            .execute_on_dest_context::<IgnoreValue>();
+    }
+
+    #[only_owner]
+    #[endpoint(setRiskAdmin)]
+    fn set_risk_admin(&self, risk_admin: ManagedAddress) {
+        self.require_non_zero_address(&risk_admin);
+
+        self.risk_admin().set(&risk_admin);
+        self.update_risk_admin_event(&risk_admin);
+    }
+
     #[endpoint(setAssetLoanToValue)]
     fn set_asset_loan_to_value(&self, asset: TokenIdentifier, loan_to_value: BigUint) {
-        self.asset_loan_to_value(&asset).set(&loan_to_value);
+        self.require_caller_risk_admin();
+        let mut config = self.get_asset_config(&asset);
+        self.settle_risk_params_ramp(&asset, &mut config, true);
+        self.require_valid_loan_to_value(&loan_to_value, &config.liquidation_threshold);
+
+        config.loan_to_value = loan_to_value;
+        self.asset_config(&asset).set(&config);
+        self.update_asset_loan_to_value_event(&asset, &config.loan_to_value);
+    }
+
+    /// Moves the loan to value and liquidation threshold of an asset linearly from their current
+    /// values to the targets, reached at `end_timestamp`, instead of cutting them at once.
+    #[endpoint(rampAssetRiskParams)]
+    fn ramp_asset_risk_params(
+        &self,
+        asset: TokenIdentifier,
+        target_loan_to_value: BigUint,
+        target_liquidation_threshold: BigUint,
+        end_timestamp: u64,
+    ) {
+        self.require_caller_risk_admin();
+        let mut config = self.get_asset_config(&asset);
+        let current_timestamp = self.blockchain().get_block_timestamp();
+        require!(
+            end_timestamp > current_timestamp,
+            "ramp must end in the future"
+        );
+        self.require_valid_liquidation_threshold(&target_liquidation_threshold);
+        self.require_valid_loan_to_value(&target_loan_to_value, &target_liquidation_threshold);
+
+        // a new ramp starts from wherever the previous one got to
+        self.settle_risk_params_ramp(&asset, &mut config, true);
+        self.asset_risk_params_ramp(&asset).set(RiskParamsRamp {
+            start_loan_to_value: config.loan_to_value.clone(),
+            start_liquidation_threshold: config.liquidation_threshold.clone(),
+            start_timestamp: current_timestamp,
+            end_timestamp,
+        });
+
+        config.loan_to_value = target_loan_to_value;
+        config.liquidation_threshold = target_liquidation_threshold;
+        self.asset_config(&asset).set(&config);
+        self.ramp_asset_risk_params_event(
+            &asset,
+            &config.loan_to_value,
+            &config.liquidation_threshold,
+            end_timestamp,
+        );
+    }
+
+    /// Ends a finished ramp, handing the liquidation threshold it reached to the pool, which
+    /// keeps the one it had when the ramp started until then. Anyone can call it.
+    #[endpoint(settleRiskParamsRamp)]
+    fn settle_finished_risk_params_ramp(&self, asset: TokenIdentifier) {
+        let mut config = self.get_asset_config(&asset);
+        self.require_no_risk_params_ramp_in_progress(&asset);
+
+        self.settle_risk_params_ramp(&asset, &mut config, true);
+        self.asset_config(&asset).set(&config);
     }
 
-    #[only_owner]
     #[endpoint(setAssetLiquidationBonus)]
     fn set_asset_liquidation_bonus(&self, asset: TokenIdentifier, liq_bonus: BigUint) {
-        self.asset_liquidation_bonus(&asset).set(&liq_bonus);
+        self.require_caller_risk_admin();
+        self.require_valid_liquidation_bonus(&liq_bonus);
+        let mut config = self.get_asset_config(&asset);
+
+        config.liquidation_bonus = liq_bonus;
+        self.asset_config(&asset).set(&config);
+        self.update_asset_liquidation_bonus_event(&asset, &config.liquidation_bonus);
+    }
+
+    /// A cap of zero leaves the market uncapped.
+    #[endpoint(setAssetSupplyCap)]
+    fn set_asset_supply_cap(&self, asset: TokenIdentifier, supply_cap: BigUint) {
+        self.require_caller_risk_admin();
+        let mut config = self.get_asset_config(&asset);
+
+        config.supply_cap = supply_cap;
+        self.asset_config(&asset).set(&config);
+        self.update_asset_supply_cap_event(&asset, &config.supply_cap);
+    }
+
+    /// A cap of zero leaves the market uncapped.
+    #[endpoint(setAssetBorrowCap)]
+    fn set_asset_borrow_cap(&self, asset: TokenIdentifier, borrow_cap: BigUint) {
+        self.require_caller_risk_admin();
+        let mut config = self.get_asset_config(&asset);
+
+        config.borrow_cap = borrow_cap;
+        self.asset_config(&asset).set(&config);
+        self.update_asset_borrow_cap_event(&asset, &config.borrow_cap);
+    }
+
+    /// Governance tokens and similar assets can be listed for supply without being lent out.
+    #[endpoint(setAssetCanBorrow)]
+    fn set_asset_can_borrow(&self, asset: TokenIdentifier, can_borrow: bool) {
+        self.require_caller_risk_admin();
+        let mut config = self.get_asset_config(&asset);
+
+        config.can_borrow = can_borrow;
+        self.asset_config(&asset).set(&config);
+        self.update_asset_can_borrow_event(&asset, can_borrow);
+    }
+
+    /// Deposits of an asset that cannot be collateral earn interest but add no borrowing power,
+    /// without needing a loan to value.
+    #[endpoint(setAssetCanBeCollateral)]
+    fn set_asset_can_be_collateral(&self, asset: TokenIdentifier, can_be_collateral: bool) {
+        self.require_caller_risk_admin();
+        let mut config = self.get_asset_config(&asset);
+
+        config.can_be_collateral = can_be_collateral;
+        self.asset_config(&asset).set(&config);
+        self.update_asset_can_be_collateral_event(&asset, can_be_collateral);
+    }
+
+    /// An account borrowing a siloed asset cannot hold any other debt, which bounds the losses
+    /// a manipulated price feed of the asset can cause.
+    #[endpoint(setAssetSiloed)]
+    fn set_asset_siloed(&self, asset: TokenIdentifier, is_siloed: bool) {
+        self.require_caller_risk_admin();
+        let mut config = self.get_asset_config(&asset);
+
+        config.is_siloed = is_siloed;
+        self.asset_config(&asset).set(&config);
+        self.update_asset_siloed_event(&asset, is_siloed);
+    }
+
+    /// Collateral in an isolated asset cannot be combined with other collateral, and only
+    /// backs borrows of isolation borrowable assets, up to the asset's debt ceiling.
+    #[endpoint(setAssetIsolated)]
+    fn set_asset_isolated(&self, asset: TokenIdentifier, is_isolated: bool) {
+        self.require_caller_risk_admin();
+        let mut config = self.get_asset_config(&asset);
+
+        config.is_isolated = is_isolated;
+        self.asset_config(&asset).set(&config);
+        self.update_asset_isolation_event(&asset, is_isolated);
+    }
+
+    /// The debt ceiling caps the principal that all accounts isolated on this asset may owe,
+    /// summed over the isolation borrowable assets with `DEBT_CEILING_DECIMALS` decimals.
+    /// Zero means no ceiling.
+    #[endpoint(setAssetDebtCeiling)]
+    fn set_asset_debt_ceiling(&self, asset: TokenIdentifier, debt_ceiling: BigUint) {
+        self.require_caller_risk_admin();
+        let mut config = self.get_asset_config(&asset);
+        if debt_ceiling != 0 {
+            for borrowable_asset in self.isolation_borrowable_assets().iter() {
+                self.require_asset_decimals_set(&borrowable_asset);
+            }
+        }
+
+        config.debt_ceiling = debt_ceiling;
+        self.asset_config(&asset).set(&config);
+        self.update_asset_debt_ceiling_event(&asset, &config.debt_ceiling);
+    }
+
+    /// Sets the ticker the asset is priced under, and the number of decimals of the token,
+    /// which normalises the amounts borrowed against isolation debt ceilings.
+    #[endpoint(setAssetOracle)]
+    fn set_asset_oracle(&self, asset: TokenIdentifier, oracle_id: ManagedBuffer, decimals: u8) {
+        self.require_caller_risk_admin();
+        let mut config = self.get_asset_config(&asset);
+
+        config.oracle_id = oracle_id;
+        config.decimals = decimals;
+        self.asset_config(&asset).set(&config);
+        self.update_asset_oracle_event(&asset, &config.oracle_id, decimals);
+    }
+
+    #[endpoint(setIsolationBorrowable)]
+    fn set_isolation_borrowable(&self, asset: TokenIdentifier, is_borrowable: bool) {
+        self.require_caller_risk_admin();
+        self.require_asset_has_pool(&asset);
+
+        if is_borrowable {
+            // borrowed amounts count against debt ceilings in the token's decimals
+            self.require_asset_decimals_set(&asset);
+            self.isolation_borrowable_assets().insert(asset.clone());
+        } else {
+            self.isolation_borrowable_assets().swap_remove(&asset);
+        }
+        self.update_isolation_borrowable_asset_event(&asset, is_borrowable);
+    }
+
+    /// Creates or updates an e-mode category. Category 0 stands for no category.
+    #[endpoint(setEModeCategory)]
+    fn set_e_mode_category(
+        &self,
+        category_id: u8,
+        loan_to_value: BigUint,
+        liquidation_threshold: BigUint,
+        liquidation_bonus: BigUint,
+    ) {
+        self.require_caller_risk_admin();
+        require!(category_id > 0, "e-mode category 0 is reserved");
+        self.require_valid_liquidation_threshold(&liquidation_threshold);
+        self.require_valid_loan_to_value(&loan_to_value, &liquidation_threshold);
+        self.require_valid_liquidation_bonus(&liquidation_bonus);
+
+        let category = EModeCategory {
+            loan_to_value,
+            liquidation_threshold,
+            liquidation_bonus,
+        };
+        self.e_mode_categories(category_id).set(&category);
+        self.update_e_mode_category_event(category_id, &category);
+    }
+
+    /// Moves an asset into an e-mode category, or out of any with category 0.
+    #[endpoint(setAssetEModeCategory)]
+    fn set_asset_e_mode_category(&self, asset: TokenIdentifier, category_id: u8) {
+        self.require_caller_risk_admin();
+        let mut config = self.get_asset_config(&asset);
+        self.require_e_mode_category_exists(category_id);
+
+        config.e_mode_category = category_id;
+        self.asset_config(&asset).set(&config);
+        self.update_asset_e_mode_category_event(&asset, category_id);
+    }
+
+    fn require_caller_risk_admin(&self) {
+        let caller = self.blockchain().get_caller();
+        require!(
+            !self.risk_admin().is_empty() && self.risk_admin().get() == caller,
+            "caller is not the risk admin"
+        );
+    }
+
+    /// The loan to value and liquidation threshold of an asset in effect now, next to their
+    /// targets. The asset config holds the targets while a ramp is in progress.
+    #[view(getAssetRiskParams)]
+    fn get_asset_risk_params(&self, asset: &TokenIdentifier) -> AssetRiskParams<Self::Api> {
+        let config = self.asset_config(asset).get();
+        let ramp_mapper = self.asset_risk_params_ramp(asset);
+        let current_timestamp = self.blockchain().get_block_timestamp();
+
+        let active_ramp = if ramp_mapper.is_empty() {
+            None
+        } else {
+            Some(ramp_mapper.get())
+        }
+        .filter(|ramp| ramp.end_timestamp > current_timestamp);
+
+        let (loan_to_value, liquidation_threshold, ramp_end_timestamp) = match active_ramp {
+            Some(ramp) => (
+                self.linear_interpolation(
+                    &ramp.start_loan_to_value,
+                    &config.loan_to_value,
+                    ramp.start_timestamp,
+                    ramp.end_timestamp,
+                    current_timestamp,
+                ),
+                self.linear_interpolation(
+                    &ramp.start_liquidation_threshold,
+                    &config.liquidation_threshold,
+                    ramp.start_timestamp,
+                    ramp.end_timestamp,
+                    current_timestamp,
+                ),
+                ramp.end_timestamp,
+            ),
+            None => (
+                config.loan_to_value.clone(),
+                config.liquidation_threshold.clone(),
+                0,
+            ),
+        };
+
+        AssetRiskParams {
+            loan_to_value,
+            liquidation_threshold,
+            target_loan_to_value: config.loan_to_value,
+            target_liquidation_threshold: config.liquidation_threshold,
+            ramp_end_timestamp,
+        }
+    }
+
+    /// Ends a ramp at the values it reached, before they are set directly, and hands the pool
+    /// the liquidation threshold reached unless the caller sets a new one on the pool right after.
+    fn settle_risk_params_ramp(
+        &self,
+        asset: &TokenIdentifier,
+        config: &mut AssetConfig<Self::Api>,
+        update_pool_threshold: bool,
+    ) {
+        if self.asset_risk_params_ramp(asset).is_empty() {
+            return;
+        }
+
+        let risk_params = self.get_asset_risk_params(asset);
+        config.loan_to_value = risk_params.loan_to_value;
+        config.liquidation_threshold = risk_params.liquidation_threshold;
+        self.asset_risk_params_ramp(asset).clear();
+        if !update_pool_threshold {
+            return;
+        }
+
+        let pool_address = self.get_pool_address(asset);
+        self.liquidity_pool_proxy(pool_address)
+            .set_liquidation_threshold(config.liquidation_threshold.clone())
+            .execute_on_dest_context::<IgnoreValue>();
+    }
+
+    fn require_no_risk_params_ramp_in_progress(&self, asset: &TokenIdentifier) {
+        let ramp_mapper = self.asset_risk_params_ramp(asset);
+        require!(
+            ramp_mapper.is_empty()
+                || ramp_mapper.get().end_timestamp <= self.blockchain().get_block_timestamp(),
+            "risk params ramp in progress"
+        );
+    }
+
+    fn get_asset_config(&self, asset: &TokenIdentifier) -> AssetConfig<Self::Api> {
+        self.require_asset_has_pool(asset);
+
+        self.asset_config(asset).get()
+    }
+
+    fn require_e_mode_category_exists(&self, category_id: u8) {
+        require!(
+            category_id == 0 || !self.e_mode_categories(category_id).is_empty(),
+            "e-mode category not found"
+        );
+    }
+
+    fn require_supply_cap_not_reached(
+        &self,
+        asset: &TokenIdentifier,
+        pool_address: &ManagedAddress,
+        amount: &BigUint,
+    ) {
+        let supply_cap = self.asset_config(asset).get().supply_cap;
+        if supply_cap == 0 {
+            return;
+        }
+
+        let total_supplied: BigUint = self
+            .liquidity_pool_proxy(pool_address.clone())
+            .get_total_supplied_capital()
+            .execute_on_dest_context();
+        require!(total_supplied + amount <= supply_cap, "supply cap reached");
+    }
+
+    fn require_borrow_cap_not_reached(
+        &self,
+        asset: &TokenIdentifier,
+        pool_address: &ManagedAddress,
+        amount: &BigUint,
+    ) {
+        let borrow_cap = self.asset_config(asset).get().borrow_cap;
+        if borrow_cap == 0 {
+            return;
+        }
+
+        let total_borrow: BigUint = self
+            .liquidity_pool_proxy(pool_address.clone())
+            .borrowed_amount()
+            .execute_on_dest_context();
+        require!(total_borrow + amount <= borrow_cap, "borrow cap reached");
+    }
+
+    fn require_asset_has_pool(&self, asset: &TokenIdentifier) {
+        require!(
+            self.pools_map().contains_key(asset),
+            "no pool found for this asset"
+        );
+    }
+
+    fn require_asset_decimals_set(&self, asset: &TokenIdentifier) {
+        require!(
+            self.asset_config(asset).get().decimals != 0,
+            "asset decimals not set"
+        );
+    }
+
+    fn require_valid_pool_config(
+        &self,
+        r_base: &BigUint,
+        r_slope1: &BigUint,
+        r_slope2: &BigUint,
+        u_optimal: &BigUint,
+        reserve_factor: &BigUint,
+        liquidation_threshold: &BigUint,
+    ) {
+        self.require_valid_pool_params(&PoolParams {
+            r_base: r_base.clone(),
+            r_slope1: r_slope1.clone(),
+            r_slope2: r_slope2.clone(),
+            u_optimal: u_optimal.clone(),
+            reserve_factor: reserve_factor.clone(),
+        });
+        self.require_valid_liquidation_threshold(liquidation_threshold);
     }
 
     #[view(getPoolAddress)]
@@ -111,24 +686,12 @@ pub trait RouterModule:
     }
 
     fn get_liquidation_bonus_non_zero(&self, token_id: &TokenIdentifier) -> BigUint {
-        let liq_bonus = self.asset_liquidation_bonus(token_id).get();
+        let liq_bonus = self.asset_config(token_id).get().liquidation_bonus;
         require!(liq_bonus > 0, "no liquidation_bonus present for asset");
 
         liq_bonus
     }
 
-    fn get_loan_to_value_exists_and_non_zero(&self, token_id: &TokenIdentifier) -> BigUint {
-        require!(
-            !self.asset_loan_to_value(token_id).is_empty(),
-            "no loan_to_value value present for asset"
-        );
-
-        let loan_to_value = self.asset_loan_to_value(token_id).get();
-        require!(loan_to_value > 0, "loan_to_value value can not be zero");
-
-        loan_to_value
-    }
-
     fn resolve_nested_async_gas_limit(&self) -> u64 {
         self.blockchain().get_gas_left() * 3 / 4
     }
@@ -136,15 +699,45 @@ pub trait RouterModule:
     #[storage_mapper("pools_map")]
     fn pools_map(&self) -> MapMapper<TokenIdentifier, ManagedAddress>;
 
+    #[view(getPoolsPendingUpgrade)]
+    #[storage_mapper("pools_pending_upgrade")]
+    fn pools_pending_upgrade(&self) -> QueueMapper<TokenIdentifier>;
+
     #[view(getPoolAllowed)]
     #[storage_mapper("pool_allowed")]
     fn pools_allowed(&self) -> SetMapper<ManagedAddress>;
 
-    #[view(getAssetLoanToValue)]
-    #[storage_mapper("asset_loan_to_value")]
-    fn asset_loan_to_value(&self, asset: &TokenIdentifier) -> SingleValueMapper<BigUint>;
+    #[view(getRiskAdmin)]
+    #[storage_mapper("risk_admin")]
+    fn risk_admin(&self) -> SingleValueMapper<ManagedAddress>;
+
+    #[view(getIsolatedAssetDebt)]
+    #[storage_mapper("isolated_asset_debt")]
+    fn isolated_asset_debt(&self, asset: &TokenIdentifier) -> SingleValueMapper<BigUint>;
+
+    /// Principal an account in isolation mode owes of a borrowed asset, in the asset's units.
+    #[view(getIsolatedAccountDebt)]
+    #[storage_mapper("isolated_account_debt")]
+    fn isolated_account_debt(
+        &self,
+        account_nonce: u64,
+        asset: &TokenIdentifier,
+    ) -> SingleValueMapper<BigUint>;
+
+    /// Isolated collateral an account's debt in `asset` counts towards, recorded when borrowed.
+    #[view(getIsolatedDebtCollateral)]
+    #[storage_mapper("isolated_debt_collateral")]
+    fn isolated_debt_collateral(
+        &self,
+        account_nonce: u64,
+        asset: &TokenIdentifier,
+    ) -> SingleValueMapper<TokenIdentifier>;
+
+    #[view(getIsolationBorrowableAssets)]
+    #[storage_mapper("isolation_borrowable_assets")]
+    fn isolation_borrowable_assets(&self) -> UnorderedSetMapper<TokenIdentifier>;
 
-    #[view(getAssetLiquidationBonus)]
-    #[storage_mapper("asset_liquidation_bonus")]
-    fn asset_liquidation_bonus(&self, asset: &TokenIdentifier) -> SingleValueMapper<BigUint>;
+    #[view(getEModeCategory)]
+    #[storage_mapper("e_mode_categories")]
+    fn e_mode_categories(&self, category_id: u8) -> SingleValueMapper<EModeCategory<Self::Api>>;
 }
@@ -1,6 +1,9 @@
 multiversx_sc::imports!();
 
-use common_structs::{BorrowPosition, DepositPosition};
+use common_structs::{AssetConfig, BorrowPosition, DepositPosition, RiskParamsRamp};
+
+/// Layout version of the lending pool storage, bumped whenever an upgrade needs a migration.
+pub const STORAGE_VERSION: u32 = 3;
 
 #[multiversx_sc::module]
 pub trait LendingStorageModule {
@@ -17,4 +20,41 @@ pub trait LendingStorageModule {
         &self,
         owner_nonce: u64,
     ) -> MapMapper<TokenIdentifier, BorrowPosition<Self::Api>>;
+
+    #[view(getBorrowAllowance)]
+    #[storage_mapper("borrow_allowance")]
+    fn borrow_allowance(
+        &self,
+        owner_nonce: u64,
+        delegate: &ManagedAddress,
+        token_id: &TokenIdentifier,
+    ) -> SingleValueMapper<BigUint>;
+
+    /// The e-mode category selected by the account, zero when none is.
+    #[view(getAccountEModeCategory)]
+    #[storage_mapper("account_e_mode_category")]
+    fn account_e_mode_category(&self, owner_nonce: u64) -> SingleValueMapper<u8>;
+
+    #[view(getAssetConfig)]
+    #[storage_mapper("asset_config")]
+    fn asset_config(&self, asset: &TokenIdentifier) -> SingleValueMapper<AssetConfig<Self::Api>>;
+
+    #[storage_mapper("asset_risk_params_ramp")]
+    fn asset_risk_params_ramp(
+        &self,
+        asset: &TokenIdentifier,
+    ) -> SingleValueMapper<RiskParamsRamp<Self::Api>>;
+
+    #[view(getStorageVersion)]
+    #[storage_mapper("storage_version")]
+    fn storage_version(&self) -> SingleValueMapper<u32>;
+
+    // Per-asset parameters of storage version 1, only read when migrating them into `asset_config`
+
+    #[storage_mapper("asset_loan_to_value")]
+    fn legacy_asset_loan_to_value(&self, asset: &TokenIdentifier) -> SingleValueMapper<BigUint>;
+
+    #[storage_mapper("asset_liquidation_bonus")]
+    fn legacy_asset_liquidation_bonus(&self, asset: &TokenIdentifier)
+        -> SingleValueMapper<BigUint>;
 }
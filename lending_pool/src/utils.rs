@@ -7,7 +7,7 @@ use common_structs::*;
 use price_aggregator_proxy::AggregatorResult;
 
 const TOKEN_ID_SUFFIX_LEN: usize = 7; // "dash" + 6 random bytes
-const DOLLAR_TICKER: &[u8] = b"USD";
+pub const DOLLAR_TICKER: &[u8] = b"USD";
 
 #[multiversx_sc::module]
 pub trait LendingUtilsModule:
@@ -26,7 +26,23 @@ pub trait LendingUtilsModule:
         }
     }
 
+    /// Price of the token in dollars, or `None` when the price feed has none.
+    fn try_get_token_price(&self, token_id: &TokenIdentifier) -> Option<BigUint> {
+        self.get_price_for_pair(
+            self.get_token_ticker(token_id.clone()),
+            ManagedBuffer::new_from_bytes(DOLLAR_TICKER),
+        )
+    }
+
     fn get_token_ticker(&self, token_id: TokenIdentifier) -> ManagedBuffer {
+        let config_mapper = self.asset_config(&token_id);
+        if !config_mapper.is_empty() {
+            let oracle_id = config_mapper.get().oracle_id;
+            if !oracle_id.is_empty() {
+                return oracle_id;
+            }
+        }
+
         let as_buffer = token_id.into_managed_buffer();
         let ticker_start_index = 0;
         let ticker_end_index = as_buffer.len() - TOKEN_ID_SUFFIX_LEN;
@@ -87,13 +103,26 @@ pub trait LendingUtilsModule:
         }
     }
 
+    /// Supply-only deposits, and deposits of assets that cannot be collateral, add no
+    /// borrowing power and cannot be seized.
+    fn counts_as_collateral(&self, deposit_position: &DepositPosition<Self::Api>) -> bool {
+        deposit_position.is_collateral
+            && self
+                .asset_config(&deposit_position.token_id)
+                .get()
+                .can_be_collateral
+    }
+
     #[inline]
     #[view(getTotalCollateralAvailable)]
     fn get_total_collateral_in_dollars(&self, account_position: u64) -> BigUint {
         let mut deposited_amount_in_dollars = BigUint::zero();
         let deposit_positions = self.deposit_positions(account_position);
 
-        for dp in deposit_positions.values() {
+        for dp in deposit_positions
+            .values()
+            .filter(|dp| self.counts_as_collateral(dp))
+        {
             let dp_data = self.get_token_price_data(dp.token_id);
             deposited_amount_in_dollars += dp.amount * dp_data.price;
         }
@@ -122,7 +151,8 @@ pub trait LendingUtilsModule:
     ) -> BigUint {
         require!(
             self.deposit_positions(liquidatee_account_nonce)
-                .contains_key(&token_to_liquidate),
+                .get(&token_to_liquidate)
+                .map_or(false, |dp| self.counts_as_collateral(&dp)),
             "Liquidatee user doesn't have this token as collateral"
         );
 
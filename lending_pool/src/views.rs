@@ -0,0 +1,221 @@
+multiversx_sc::imports!();
+
+/// Upper bound on the accounts scanned by one `getLiquidationCandidates` call
+pub const MAX_ACCOUNTS_PER_PAGE: usize = 50;
+
+use crate::{events, factory, math, proxy, router, storage, utils};
+
+use common_structs::{EModeCategory, LiquidationCandidate, MarketOverview, PositionValue};
+use liquidity_pool::liq_storage::ProxyTrait as _;
+use liquidity_pool::liq_utils::ProxyTrait as _;
+
+#[multiversx_sc::module]
+pub trait ViewsModule:
+    router::RouterModule
+    + proxy::ProxyModule
+    + factory::FactoryModule
+    + events::EventsModule
+    + storage::LendingStorageModule
+    + utils::LendingUtilsModule
+    + math::LendingMathModule
+    + common_checks::ChecksModule
+    + common_tokens::AccountTokenModule
+    + price_aggregator_proxy::PriceAggregatorModule
+{
+    #[view(getMarkets)]
+    fn get_markets(&self) -> MultiValueEncoded<MarketOverview<Self::Api>> {
+        let mut markets = MultiValueEncoded::new();
+        for (asset_id, pool_address) in self.pools_map().iter() {
+            markets.push(self.get_market_overview(asset_id, pool_address));
+        }
+
+        markets
+    }
+
+    /// Scans the accounts at positions `from_index..from_index + page_size` of
+    /// `account_positions` (starting at 1) and returns those whose health factor
+    /// is below `max_health_factor`. Accounts without debt are never candidates, and
+    /// accounts holding an asset without a price are skipped.
+    #[view(getLiquidationCandidates)]
+    fn get_liquidation_candidates(
+        &self,
+        from_index: usize,
+        page_size: usize,
+        max_health_factor: BigUint,
+    ) -> MultiValueEncoded<LiquidationCandidate<Self::Api>> {
+        require!(from_index > 0, "from_index starts at 1");
+        require!(
+            page_size > 0 && page_size <= MAX_ACCOUNTS_PER_PAGE,
+            "invalid page size"
+        );
+
+        let account_positions = self.account_positions();
+        let to_index = core::cmp::min(from_index + page_size - 1, account_positions.len());
+
+        let mut candidates = MultiValueEncoded::new();
+        for index in from_index..=to_index {
+            let account_nonce = account_positions.get_by_index(index);
+            if let Some(candidate) = self.get_liquidation_candidate(account_nonce) {
+                if candidate.health_factor < max_health_factor {
+                    candidates.push(candidate);
+                }
+            }
+        }
+
+        candidates
+    }
+
+    fn get_liquidation_candidate(
+        &self,
+        account_nonce: u64,
+    ) -> Option<LiquidationCandidate<Self::Api>> {
+        if self.borrow_positions(account_nonce).is_empty() {
+            return None;
+        }
+
+        let mut debts = ManagedVec::new();
+        let mut total_borrow_in_dollars = BigUint::zero();
+        for bp in self.borrow_positions(account_nonce).values() {
+            let value_in_dollars = &bp.amount * &self.try_get_token_price(&bp.token_id)?;
+            total_borrow_in_dollars += &value_in_dollars;
+            debts.push(PositionValue {
+                token_id: bp.token_id,
+                amount: bp.amount,
+                value_in_dollars,
+            });
+        }
+
+        let e_mode_category = self.get_active_e_mode_category(account_nonce);
+        let mut collaterals = ManagedVec::new();
+        let mut total_collateral_in_dollars = BigUint::zero();
+        let mut weighted_collateral_in_dollars = BigUint::zero();
+        for dp in self.deposit_positions(account_nonce).values() {
+            // supply-only deposits can be neither counted nor seized
+            if !self.counts_as_collateral(&dp) {
+                continue;
+            }
+
+            let value_in_dollars = &dp.amount * &self.try_get_token_price(&dp.token_id)?;
+            let liquidation_threshold =
+                self.get_collateral_liquidation_threshold(&dp.token_id, &e_mode_category);
+
+            total_collateral_in_dollars += &value_in_dollars;
+            weighted_collateral_in_dollars += &value_in_dollars * &liquidation_threshold;
+            collaterals.push(PositionValue {
+                token_id: dp.token_id,
+                amount: dp.amount,
+                value_in_dollars,
+            });
+        }
+
+        // an account whose debt is worth nothing yet cannot be liquidated
+        if total_borrow_in_dollars == 0 {
+            return None;
+        }
+
+        // weighted as in `liquidate`, by the e-mode category or each asset's own threshold
+        let health_factor = weighted_collateral_in_dollars / &total_borrow_in_dollars;
+
+        Some(LiquidationCandidate {
+            account_nonce,
+            health_factor,
+            total_collateral_in_dollars,
+            total_borrow_in_dollars,
+            collaterals,
+            debts,
+        })
+    }
+
+    fn get_market_overview(
+        &self,
+        asset_id: TokenIdentifier,
+        pool_address: ManagedAddress,
+    ) -> MarketOverview<Self::Api> {
+        let reserves: BigUint = self
+            .liquidity_pool_proxy(pool_address.clone())
+            .reserves()
+            .execute_on_dest_context();
+        let total_borrow: BigUint = self
+            .liquidity_pool_proxy(pool_address.clone())
+            .borrowed_amount()
+            .execute_on_dest_context();
+        let capital_utilisation: BigUint = self
+            .liquidity_pool_proxy(pool_address.clone())
+            .get_capital_utilisation()
+            .execute_on_dest_context();
+        let borrow_rate: BigUint = self
+            .liquidity_pool_proxy(pool_address.clone())
+            .get_borrow_rate()
+            .execute_on_dest_context();
+        let deposit_rate: BigUint = self
+            .liquidity_pool_proxy(pool_address.clone())
+            .get_deposit_rate()
+            .execute_on_dest_context();
+        let config = self.asset_config(&asset_id).get();
+        let risk_params = self.get_asset_risk_params(&asset_id);
+
+        // a missing price feed should not hide the other markets
+        let price = self.try_get_token_price(&asset_id);
+
+        MarketOverview {
+            asset_id,
+            pool_address,
+            reserves,
+            total_borrow,
+            capital_utilisation,
+            borrow_rate,
+            deposit_rate,
+            loan_to_value: risk_params.loan_to_value,
+            liquidation_bonus: config.liquidation_bonus,
+            liquidation_threshold: risk_params.liquidation_threshold,
+            target_loan_to_value: risk_params.target_loan_to_value,
+            target_liquidation_threshold: risk_params.target_liquidation_threshold,
+            price,
+            supply_cap: config.supply_cap,
+            borrow_cap: config.borrow_cap,
+            can_borrow: config.can_borrow,
+            can_be_collateral: config.can_be_collateral,
+            is_siloed: config.is_siloed,
+        }
+    }
+
+    /// Returns the e-mode category of the account when all its collaterals and debts belong to it.
+    fn get_active_e_mode_category(&self, account_nonce: u64) -> Option<EModeCategory<Self::Api>> {
+        let category_id = self.account_e_mode_category(account_nonce).get();
+        if category_id == 0 {
+            return None;
+        }
+
+        let in_category = |token_id: &TokenIdentifier| {
+            self.asset_config(token_id).get().e_mode_category == category_id
+        };
+        let collaterals_in_category = self
+            .deposit_positions(account_nonce)
+            .values()
+            .filter(|dp| self.counts_as_collateral(dp))
+            .all(|dp| in_category(&dp.token_id));
+        let debts_in_category = self
+            .borrow_positions(account_nonce)
+            .keys()
+            .all(|token_id| in_category(&token_id));
+
+        if collaterals_in_category && debts_in_category {
+            Some(self.e_mode_categories(category_id).get())
+        } else {
+            None
+        }
+    }
+
+    /// Liquidation threshold weighing a collateral: the one of the account's active e-mode
+    /// category, or else the one in effect for the asset.
+    fn get_collateral_liquidation_threshold(
+        &self,
+        token_id: &TokenIdentifier,
+        e_mode_category: &Option<EModeCategory<Self::Api>>,
+    ) -> BigUint {
+        match e_mode_category {
+            Some(category) => category.liquidation_threshold.clone(),
+            None => self.get_asset_risk_params(token_id).liquidation_threshold,
+        }
+    }
+}
@@ -7,6 +7,7 @@ pub const USDC_TICKER: &[u8] = b"USDC";
 pub const USDC_TOKEN_ID: &[u8] = b"USDC-123456";
 pub const EGLD_TICKER: &[u8] = b"WEGLD";
 pub const EGLD_TOKEN_ID: &[u8] = b"WEGLD-123456";
+pub const MEX_TOKEN_ID: &[u8] = b"MEX-123456";
 pub const EGLD_PRICE_IN_DOLLARS: u64 = 20_000; // $200
 pub const EGLD_PRICE_DROPPED_IN_DOLLARS: u64 = 14_000; // $140
 pub const USDC_PRICE_IN_DOLLARS: u64 = 100; // $1
@@ -17,6 +18,7 @@ pub const R_SLOPE2: u64 = 1_000_000_000;
 pub const U_OPTIMAL: u64 = 800_000_000;
 pub const RESERVE_FACTOR: u64 = 100_000_000;
 pub const LIQ_THRESOLD: u64 = 700_000_000;
+pub const PERCENT: u64 = 10_000_000_000_000_000; // 1% of BP
 pub const ACCOUNT_TOKEN: &[u8] = b"LACC-abcdef";
 pub const ACCOUNT_TICKER: &[u8] = b"LACC";
 
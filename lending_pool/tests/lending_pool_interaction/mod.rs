@@ -1,18 +1,27 @@
 use multiversx_sc::{
-    codec::Empty,
-    types::{Address, BigUint, EsdtLocalRole},
+    codec::{Empty, TopDecode, TopEncode},
+    storage::{
+        mappers::{MapMapper, StorageMapper},
+        StorageKey,
+    },
+    types::{Address, BigUint, EsdtLocalRole, ManagedBuffer, MultiValueEncoded, TokenIdentifier},
 };
 use multiversx_sc_scenario::{
     managed_address, managed_biguint, managed_token_id, rust_biguint,
-    whitebox::{BlockchainStateWrapper, ContractObjWrapper},
+    whitebox::{BlockchainStateWrapper, ContractObjWrapper, TxResult, TxTokenTransfer},
+    multiversx_chain_vm::tx_mock::TxContextStack,
     DebugApi,
 };
 use lending_pool::{
-    router::RouterModule, storage::LendingStorageModule, AccountTokenModule, BorrowPosition,
-    DepositPosition, LendingPool, BP,
+    factory::FactoryModule, router::RouterModule, storage::LendingStorageModule,
+    views::ViewsModule, AccountTokenModule, AssetConfig, BorrowPosition, DepositPosition,
+    InterestRateModel, LendingPool, BP,
 };
 use liquidity_pool::LiquidityPool;
-use liquidity_pool::{liq_storage::StorageModule, liquidity::LiquidityModule};
+use liquidity_pool::{
+    liq_math::MathModule, liq_storage::StorageModule, liq_utils::UtilsModule,
+    liquidity::LiquidityModule,
+};
 use price_aggregator_proxy::PriceAggregatorModule;
 
 use crate::{
@@ -30,6 +39,7 @@ where
     PriceAggregatorObjBuilder: 'static + Copy + Fn() -> aggregator_mock::ContractObj<DebugApi>,
 {
     pub owner_addr: Address,
+    pub risk_admin_addr: Address,
     pub first_user_addr: Address,
     pub second_user_addr: Address,
     pub third_user_addr: Address,
@@ -62,6 +72,7 @@ where
         let rust_zero = rust_biguint!(0u64);
         let mut b_mock = BlockchainStateWrapper::new();
         let owner_addr = b_mock.create_user_account(&rust_zero);
+        let risk_admin_addr = b_mock.create_user_account(&rust_zero);
         let first_user_addr = b_mock.create_user_account(&rust_biguint!(100_000_000));
         let second_user_addr = b_mock.create_user_account(&rust_biguint!(100_000_000));
         let third_user_addr = b_mock.create_user_account(&rust_biguint!(100_000_000));
@@ -120,10 +131,11 @@ where
                     );
                     sc.pools_allowed()
                         .insert(managed_address!(&liquidity_pool_usdc_wrapper.address_ref()));
-                    sc.set_asset_liquidation_bonus(
-                        managed_token_id!(USDC_TOKEN_ID),
-                        managed_biguint!(BP / 20),
-                    );
+                    let mut asset_config = AssetConfig::new(managed_biguint!(LIQ_THRESOLD), 0);
+                    asset_config.liquidation_bonus = managed_biguint!(BP / 20);
+                    sc.asset_config(&managed_token_id!(USDC_TOKEN_ID))
+                        .set(&asset_config);
+                    sc.set_risk_admin(managed_address!(&risk_admin_addr));
                 },
             )
             .assert_ok();
@@ -170,10 +182,10 @@ where
                     sc.pools_allowed()
                         .insert(managed_address!(&liquidity_pool_egld_wrapper.address_ref()));
 
-                    sc.set_asset_liquidation_bonus(
-                        managed_token_id!(EGLD_TOKEN_ID),
-                        managed_biguint!(BP / 20),
-                    );
+                    let mut asset_config = AssetConfig::new(managed_biguint!(LIQ_THRESOLD), 0);
+                    asset_config.liquidation_bonus = managed_biguint!(BP / 20);
+                    sc.asset_config(&managed_token_id!(EGLD_TOKEN_ID))
+                        .set(&asset_config);
                 },
             )
             .assert_ok();
@@ -190,6 +202,7 @@ where
 
         Self {
             owner_addr,
+            risk_admin_addr,
             first_user_addr,
             second_user_addr,
             third_user_addr,
@@ -243,6 +256,352 @@ where
             .assert_ok();
     }
 
+    pub fn try_exit_market(&mut self, user_addr: &Address, account_nonce: u64) -> TxResult {
+        self.b_mock.execute_esdt_transfer(
+            user_addr,
+            &self.lending_pool_wrapper,
+            ACCOUNT_TOKEN,
+            account_nonce,
+            &rust_biguint!(1),
+            |sc| {
+                sc.exit_market();
+            },
+        )
+    }
+
+    /* Adds collateral through the Lending SC, sending the account token along with the payment */
+    pub fn add_collateral_through_lending(
+        &mut self,
+        user_addr: &Address,
+        token_id: &[u8],
+        account_nonce: u64,
+        amount: u64,
+    ) -> Vec<Vec<u8>> {
+        let transfers = [
+            TxTokenTransfer {
+                token_identifier: ACCOUNT_TOKEN.to_vec(),
+                nonce: account_nonce,
+                value: rust_biguint!(1),
+            },
+            TxTokenTransfer {
+                token_identifier: token_id.to_vec(),
+                nonce: 0,
+                value: rust_biguint!(amount),
+            },
+        ];
+
+        let mut events = Vec::new();
+        self.b_mock
+            .execute_esdt_multi_transfer(user_addr, &self.lending_pool_wrapper, &transfers, |sc| {
+                sc.add_collateral();
+                events = emitted_events();
+            })
+            .assert_ok();
+
+        events
+    }
+
+    /* Adds collateral to the given account from any address, without the account token */
+    pub fn add_collateral_on_behalf(
+        &mut self,
+        caller_addr: &Address,
+        token_id: &[u8],
+        account_nonce: u64,
+        amount: u64,
+    ) -> TxResult {
+        self.b_mock.execute_esdt_transfer(
+            caller_addr,
+            &self.lending_pool_wrapper,
+            token_id,
+            0,
+            &rust_biguint!(amount),
+            |sc| {
+                sc.add_collateral_on_behalf(account_nonce);
+            },
+        )
+    }
+
+    /* Checks the deposit position of an account, None meaning no position is stored */
+    pub fn check_deposit_position(
+        &mut self,
+        account_nonce: u64,
+        token_id: &[u8],
+        expected_amount: Option<u64>,
+    ) {
+        self.b_mock
+            .execute_query(&self.lending_pool_wrapper, |sc| {
+                let position = sc
+                    .deposit_positions(account_nonce)
+                    .get(&managed_token_id!(token_id))
+                    .map(|position| position.amount);
+                assert_eq!(
+                    position,
+                    expected_amount.map(|amount| managed_biguint!(amount))
+                );
+            })
+            .assert_ok();
+    }
+
+    /* Sends the account token followed by one payment per (token, amount) to a multi-asset endpoint */
+    fn account_token_with_payments(
+        account_nonce: u64,
+        payments: &[(&[u8], u64)],
+    ) -> Vec<TxTokenTransfer> {
+        let mut transfers = vec![TxTokenTransfer {
+            token_identifier: ACCOUNT_TOKEN.to_vec(),
+            nonce: account_nonce,
+            value: rust_biguint!(1),
+        }];
+        for (token_id, amount) in payments {
+            transfers.push(TxTokenTransfer {
+                token_identifier: token_id.to_vec(),
+                nonce: 0,
+                value: rust_biguint!(*amount),
+            });
+        }
+
+        transfers
+    }
+
+    pub fn add_collateral_multi(
+        &mut self,
+        user_addr: &Address,
+        account_nonce: u64,
+        payments: &[(&[u8], u64)],
+    ) -> TxResult {
+        let transfers = Self::account_token_with_payments(account_nonce, payments);
+        self.b_mock.execute_esdt_multi_transfer(
+            user_addr,
+            &self.lending_pool_wrapper,
+            &transfers,
+            |sc| {
+                sc.add_collateral_multi();
+            },
+        )
+    }
+
+    pub fn repay_multi(
+        &mut self,
+        user_addr: &Address,
+        account_nonce: u64,
+        payments: &[(&[u8], u64)],
+    ) -> TxResult {
+        let transfers = Self::account_token_with_payments(account_nonce, payments);
+        self.b_mock.execute_esdt_multi_transfer(
+            user_addr,
+            &self.lending_pool_wrapper,
+            &transfers,
+            |sc| {
+                sc.repay_multi();
+            },
+        )
+    }
+
+    pub fn borrow_multi(
+        &mut self,
+        user_addr: &Address,
+        account_nonce: u64,
+        borrows: &[(&[u8], u64)],
+    ) -> TxResult {
+        self.b_mock.execute_esdt_transfer(
+            user_addr,
+            &self.lending_pool_wrapper,
+            ACCOUNT_TOKEN,
+            account_nonce,
+            &rust_biguint!(1),
+            |sc| {
+                let mut args = MultiValueEncoded::new();
+                for (token_id, amount) in borrows {
+                    args.push((managed_token_id!(*token_id), managed_biguint!(*amount)).into());
+                }
+                sc.borrow_multi(args);
+            },
+        )
+    }
+
+    pub fn remove_collateral_multi(
+        &mut self,
+        user_addr: &Address,
+        account_nonce: u64,
+        withdrawals: &[(&[u8], u64)],
+    ) -> TxResult {
+        self.b_mock.execute_esdt_transfer(
+            user_addr,
+            &self.lending_pool_wrapper,
+            ACCOUNT_TOKEN,
+            account_nonce,
+            &rust_biguint!(1),
+            |sc| {
+                let mut args = MultiValueEncoded::new();
+                for (token_id, amount) in withdrawals {
+                    args.push((managed_token_id!(*token_id), managed_biguint!(*amount)).into());
+                }
+                sc.remove_collateral_multi(args);
+            },
+        )
+    }
+
+    /* Borrows through the Lending SC, sending the account token along */
+    pub fn borrow_through_lending(
+        &mut self,
+        user_addr: &Address,
+        token_id: &[u8],
+        account_nonce: u64,
+        amount: u64,
+    ) -> TxResult {
+        self.b_mock.execute_esdt_transfer(
+            user_addr,
+            &self.lending_pool_wrapper,
+            ACCOUNT_TOKEN,
+            account_nonce,
+            &rust_biguint!(1),
+            |sc| {
+                sc.borrow(managed_token_id!(token_id), managed_biguint!(amount));
+            },
+        )
+    }
+
+    pub fn approve_borrow_delegation(
+        &mut self,
+        user_addr: &Address,
+        account_nonce: u64,
+        delegate_addr: &Address,
+        token_id: &[u8],
+        amount: u64,
+    ) -> TxResult {
+        self.b_mock.execute_esdt_transfer(
+            user_addr,
+            &self.lending_pool_wrapper,
+            ACCOUNT_TOKEN,
+            account_nonce,
+            &rust_biguint!(1),
+            |sc| {
+                sc.approve_borrow_delegation(
+                    managed_address!(delegate_addr),
+                    managed_token_id!(token_id),
+                    managed_biguint!(amount),
+                );
+            },
+        )
+    }
+
+    pub fn borrow_on_behalf(
+        &mut self,
+        delegate_addr: &Address,
+        account_nonce: u64,
+        token_id: &[u8],
+        amount: u64,
+    ) -> TxResult {
+        self.b_mock.execute_tx(
+            delegate_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.borrow_on_behalf(
+                    account_nonce,
+                    managed_token_id!(token_id),
+                    managed_biguint!(amount),
+                );
+            },
+        )
+    }
+
+    pub fn check_borrow_allowance(
+        &mut self,
+        account_nonce: u64,
+        delegate_addr: &Address,
+        token_id: &[u8],
+        expected_allowance: u64,
+    ) {
+        self.b_mock
+            .execute_query(&self.lending_pool_wrapper, |sc| {
+                let allowance = sc
+                    .borrow_allowance(
+                        account_nonce,
+                        &managed_address!(delegate_addr),
+                        &managed_token_id!(token_id),
+                    )
+                    .get();
+                assert_eq!(allowance, managed_biguint!(expected_allowance));
+            })
+            .assert_ok();
+    }
+
+    /* Repays debt of the given account from any address, without the account token */
+    pub fn repay_on_behalf(
+        &mut self,
+        payer_addr: &Address,
+        token_id: &[u8],
+        account_nonce: u64,
+        amount: u64,
+    ) -> TxResult {
+        self.b_mock.execute_esdt_transfer(
+            payer_addr,
+            &self.lending_pool_wrapper,
+            token_id,
+            0,
+            &rust_biguint!(amount),
+            |sc| {
+                sc.repay_on_behalf(account_nonce);
+            },
+        )
+    }
+
+    pub fn set_collateral_usage(
+        &mut self,
+        user_addr: &Address,
+        account_nonce: u64,
+        token_id: &[u8],
+        is_collateral: bool,
+    ) -> TxResult {
+        self.b_mock.execute_esdt_transfer(
+            user_addr,
+            &self.lending_pool_wrapper,
+            ACCOUNT_TOKEN,
+            account_nonce,
+            &rust_biguint!(1),
+            |sc| {
+                if is_collateral {
+                    sc.enable_as_collateral(managed_token_id!(token_id));
+                } else {
+                    sc.disable_as_collateral(managed_token_id!(token_id));
+                }
+            },
+        )
+    }
+
+    pub fn accrue_account(&mut self, caller_addr: &Address, account_nonce: u64) -> TxResult {
+        self.b_mock.execute_tx(
+            caller_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.accrue_account(account_nonce);
+            },
+        )
+    }
+
+    /* Checks the borrow position of an account, None meaning no position is stored */
+    pub fn check_borrow_position(
+        &mut self,
+        account_nonce: u64,
+        token_id: &[u8],
+        expected_amount: Option<u64>,
+    ) {
+        self.b_mock
+            .execute_query(&self.lending_pool_wrapper, |sc| {
+                let position = sc
+                    .borrow_positions(account_nonce)
+                    .get(&managed_token_id!(token_id))
+                    .map(|position| position.amount);
+                assert_eq!(
+                    position,
+                    expected_amount.map(|amount| managed_biguint!(amount))
+                );
+            })
+            .assert_ok();
+    }
+
     pub fn add_collateral(
         &mut self,
         user_addr: &Address,
@@ -544,6 +903,101 @@ where
         );
     }
 
+    /* Writes the account's positions directly in the Lending SC storage */
+    pub fn set_account_positions(
+        &mut self,
+        account_nonce: u64,
+        deposits: &[(&[u8], u64)],
+        borrows: &[(&[u8], u64)],
+    ) {
+        self.b_mock
+            .execute_tx(
+                &self.owner_addr,
+                &self.lending_pool_wrapper,
+                &rust_biguint!(0),
+                |sc| {
+                    for (token_id, amount) in deposits {
+                        sc.deposit_positions(account_nonce).insert(
+                            managed_token_id!(*token_id),
+                            DepositPosition::new(
+                                managed_token_id!(*token_id),
+                                managed_biguint!(*amount),
+                                account_nonce,
+                                1,
+                                managed_biguint!(BP),
+                            ),
+                        );
+                    }
+                    for (token_id, amount) in borrows {
+                        sc.borrow_positions(account_nonce).insert(
+                            managed_token_id!(*token_id),
+                            BorrowPosition::new(
+                                managed_token_id!(*token_id),
+                                managed_biguint!(*amount),
+                                account_nonce,
+                                1,
+                                managed_biguint!(BP),
+                            ),
+                        );
+                    }
+                },
+            )
+            .assert_ok();
+    }
+
+    /* Returns (account nonce, health factor, collateral in dollars, borrow in dollars) of each candidate */
+    pub fn get_liquidation_candidates(
+        &mut self,
+        from_index: usize,
+        page_size: usize,
+        max_health_factor: u64,
+    ) -> Vec<(u64, u64, u64, u64)> {
+        let mut candidates = Vec::new();
+        self.b_mock
+            .execute_query(&self.lending_pool_wrapper, |sc| {
+                for candidate in sc
+                    .get_liquidation_candidates(
+                        from_index,
+                        page_size,
+                        managed_biguint!(max_health_factor),
+                    )
+                    .into_iter()
+                {
+                    let debts_in_dollars = candidate.debts.iter().fold(0, |total, debt| {
+                        total + debt.value_in_dollars.to_u64().unwrap()
+                    });
+                    assert_eq!(
+                        managed_biguint!(debts_in_dollars),
+                        candidate.total_borrow_in_dollars,
+                        "Debts don't add up to the total borrow!"
+                    );
+
+                    candidates.push((
+                        candidate.account_nonce,
+                        candidate.health_factor.to_u64().unwrap(),
+                        candidate.total_collateral_in_dollars.to_u64().unwrap(),
+                        candidate.total_borrow_in_dollars.to_u64().unwrap(),
+                    ));
+                }
+            })
+            .assert_ok();
+
+        candidates
+    }
+
+    pub fn check_liquidation_candidates_error(
+        &mut self,
+        from_index: usize,
+        page_size: usize,
+        expected_message: &str,
+    ) {
+        self.b_mock
+            .execute_query(&self.lending_pool_wrapper, |sc| {
+                let _ = sc.get_liquidation_candidates(from_index, page_size, managed_biguint!(BP));
+            })
+            .assert_user_error(expected_message);
+    }
+
     pub fn get_liquidity_pool_wrapper(
         &self,
         token_id: &[u8],
@@ -598,4 +1052,996 @@ where
             })
             .assert_ok();
     }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_liquidity_pool(
+        &mut self,
+        token_id: &[u8],
+        r_base: u64,
+        r_slope1: u64,
+        r_slope2: u64,
+        u_optimal: u64,
+        reserve_factor: u64,
+        liquidation_threshold: u64,
+    ) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.owner_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.create_liquidity_pool(
+                    managed_token_id!(token_id),
+                    managed_biguint!(r_base),
+                    managed_biguint!(r_slope1),
+                    managed_biguint!(r_slope2),
+                    managed_biguint!(u_optimal),
+                    managed_biguint!(reserve_factor),
+                    managed_biguint!(liquidation_threshold),
+                    18,
+                );
+            },
+        )
+    }
+
+    pub fn set_asset_loan_to_value(&mut self, token_id: &[u8], loan_to_value: u64) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.risk_admin_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_asset_loan_to_value(
+                    managed_token_id!(token_id),
+                    managed_biguint!(loan_to_value),
+                );
+            },
+        )
+    }
+
+    pub fn ramp_asset_risk_params(
+        &mut self,
+        token_id: &[u8],
+        target_loan_to_value: u64,
+        target_liquidation_threshold: u64,
+        end_timestamp: u64,
+    ) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.risk_admin_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.ramp_asset_risk_params(
+                    managed_token_id!(token_id),
+                    managed_biguint!(target_loan_to_value),
+                    managed_biguint!(target_liquidation_threshold),
+                    end_timestamp,
+                );
+            },
+        )
+    }
+
+    pub fn settle_risk_params_ramp(&mut self, caller_addr: &Address, token_id: &[u8]) -> TxResult {
+        self.b_mock.execute_tx(
+            caller_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.settle_finished_risk_params_ramp(managed_token_id!(token_id));
+            },
+        )
+    }
+
+    /* Checks the current and target risk parameters of an asset, as in (LTV, threshold) pairs */
+    pub fn check_asset_risk_params(
+        &mut self,
+        token_id: &[u8],
+        expected_current: (u64, u64),
+        expected_target: (u64, u64),
+        expected_ramp_end_timestamp: u64,
+    ) {
+        self.b_mock
+            .execute_query(&self.lending_pool_wrapper, |sc| {
+                let risk_params = sc.get_asset_risk_params(&managed_token_id!(token_id));
+                assert_eq!(
+                    risk_params.loan_to_value,
+                    managed_biguint!(expected_current.0),
+                    "Current loan to value doesn't match!"
+                );
+                assert_eq!(
+                    risk_params.liquidation_threshold,
+                    managed_biguint!(expected_current.1),
+                    "Current liquidation threshold doesn't match!"
+                );
+                assert_eq!(
+                    risk_params.target_loan_to_value,
+                    managed_biguint!(expected_target.0),
+                    "Target loan to value doesn't match!"
+                );
+                assert_eq!(
+                    risk_params.target_liquidation_threshold,
+                    managed_biguint!(expected_target.1),
+                    "Target liquidation threshold doesn't match!"
+                );
+                assert_eq!(risk_params.ramp_end_timestamp, expected_ramp_end_timestamp);
+            })
+            .assert_ok();
+    }
+
+    pub fn set_asset_liquidation_bonus(&mut self, token_id: &[u8], liq_bonus: u64) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.risk_admin_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_asset_liquidation_bonus(
+                    managed_token_id!(token_id),
+                    managed_biguint!(liq_bonus),
+                );
+            },
+        )
+    }
+
+    pub fn set_asset_supply_cap(&mut self, token_id: &[u8], supply_cap: u64) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.risk_admin_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_asset_supply_cap(managed_token_id!(token_id), managed_biguint!(supply_cap));
+            },
+        )
+    }
+
+    pub fn set_asset_borrow_cap(&mut self, token_id: &[u8], borrow_cap: u64) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.risk_admin_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_asset_borrow_cap(managed_token_id!(token_id), managed_biguint!(borrow_cap));
+            },
+        )
+    }
+
+    pub fn set_risk_admin(&mut self, risk_admin_addr: &Address) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.owner_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_risk_admin(managed_address!(risk_admin_addr));
+            },
+        )
+    }
+
+    pub fn set_asset_oracle(
+        &mut self,
+        token_id: &[u8],
+        oracle_id: &[u8],
+        decimals: u8,
+    ) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.risk_admin_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_asset_oracle(
+                    managed_token_id!(token_id),
+                    ManagedBuffer::from(oracle_id),
+                    decimals,
+                );
+            },
+        )
+    }
+
+    /* Brings the parameters of an asset back to the storage layout of version 1 */
+    pub fn restore_legacy_asset_params(&mut self, token_id: &[u8], loan_to_value: u64) {
+        self.b_mock
+            .execute_tx(
+                &self.owner_addr,
+                &self.lending_pool_wrapper,
+                &rust_biguint!(0),
+                |sc| {
+                    let asset = managed_token_id!(token_id);
+                    let asset_config = sc.asset_config(&asset).take();
+                    sc.legacy_asset_loan_to_value(&asset)
+                        .set(managed_biguint!(loan_to_value));
+                    sc.legacy_asset_liquidation_bonus(&asset)
+                        .set(asset_config.liquidation_bonus);
+                    sc.storage_version().set(1);
+                },
+            )
+            .assert_ok();
+    }
+
+    /* Stores a deposit position in the layout used before the collateral flag */
+    pub fn restore_legacy_deposit_position(
+        &mut self,
+        account_nonce: u64,
+        token_id: &[u8],
+        amount: u64,
+    ) {
+        self.b_mock
+            .execute_tx(
+                &self.owner_addr,
+                &self.lending_pool_wrapper,
+                &rust_biguint!(0),
+                |sc| {
+                    raw_deposit_positions::<LegacyDepositPosition>(account_nonce).insert(
+                        managed_token_id!(token_id),
+                        (
+                            managed_token_id!(token_id),
+                            managed_biguint!(amount),
+                            account_nonce,
+                            0,
+                            managed_biguint!(BP),
+                        ),
+                    );
+                    sc.storage_version().set(2);
+                },
+            )
+            .assert_ok();
+    }
+
+    pub fn check_stored_collateral_flag(
+        &mut self,
+        account_nonce: u64,
+        token_id: &[u8],
+        expected_is_collateral: bool,
+    ) {
+        self.b_mock
+            .execute_query(&self.lending_pool_wrapper, |_sc| {
+                let stored = raw_deposit_positions::<StoredDepositPosition>(account_nonce)
+                    .get(&managed_token_id!(token_id))
+                    .unwrap();
+                assert_eq!(
+                    stored.5, expected_is_collateral,
+                    "Stored collateral flag doesn't match!"
+                );
+            })
+            .assert_ok();
+    }
+
+    /* Fails unless the position is still stored in the layout without the collateral flag */
+    pub fn check_legacy_deposit_position(&mut self, account_nonce: u64, token_id: &[u8]) {
+        self.b_mock
+            .execute_query(&self.lending_pool_wrapper, |_sc| {
+                raw_deposit_positions::<LegacyDepositPosition>(account_nonce)
+                    .get(&managed_token_id!(token_id))
+                    .unwrap();
+            })
+            .assert_ok();
+    }
+
+    /* Runs the init of the Lending SC again, as an upgrade does */
+    pub fn upgrade_lending_pool(&mut self, asset_decimals: &[(&[u8], u8)]) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.owner_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                let lp_template_address = sc.liq_pool_template_address().get();
+                let mut decimals_args = MultiValueEncoded::new();
+                for (token_id, decimals) in asset_decimals {
+                    decimals_args.push((managed_token_id!(*token_id), *decimals).into());
+                }
+                sc.init(lp_template_address, decimals_args);
+            },
+        )
+    }
+
+    pub fn set_asset_can_borrow(&mut self, token_id: &[u8], can_borrow: bool) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.risk_admin_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_asset_can_borrow(managed_token_id!(token_id), can_borrow);
+            },
+        )
+    }
+
+    pub fn set_asset_can_be_collateral(
+        &mut self,
+        token_id: &[u8],
+        can_be_collateral: bool,
+    ) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.risk_admin_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_asset_can_be_collateral(managed_token_id!(token_id), can_be_collateral);
+            },
+        )
+    }
+
+    pub fn set_asset_siloed(&mut self, token_id: &[u8], is_siloed: bool) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.risk_admin_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_asset_siloed(managed_token_id!(token_id), is_siloed);
+            },
+        )
+    }
+
+    /* Checks the asset flags reported by getMarkets */
+    pub fn check_market_flags(
+        &mut self,
+        token_id: &[u8],
+        expected_can_borrow: bool,
+        expected_can_be_collateral: bool,
+        expected_is_siloed: bool,
+    ) {
+        self.b_mock
+            .execute_query(&self.lending_pool_wrapper, |sc| {
+                let market = sc
+                    .get_markets()
+                    .into_iter()
+                    .find(|market| market.asset_id == managed_token_id!(token_id))
+                    .expect("Market is not listed!");
+
+                assert_eq!(market.can_borrow, expected_can_borrow);
+                assert_eq!(market.can_be_collateral, expected_can_be_collateral);
+                assert_eq!(market.is_siloed, expected_is_siloed);
+            })
+            .assert_ok();
+    }
+
+    pub fn set_asset_isolated(&mut self, token_id: &[u8], is_isolated: bool) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.risk_admin_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_asset_isolated(managed_token_id!(token_id), is_isolated);
+            },
+        )
+    }
+
+    pub fn set_asset_debt_ceiling(&mut self, token_id: &[u8], debt_ceiling: u64) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.risk_admin_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_asset_debt_ceiling(
+                    managed_token_id!(token_id),
+                    managed_biguint!(debt_ceiling),
+                );
+            },
+        )
+    }
+
+    pub fn set_isolation_borrowable(&mut self, token_id: &[u8], is_borrowable: bool) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.risk_admin_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_isolation_borrowable(managed_token_id!(token_id), is_borrowable);
+            },
+        )
+    }
+
+    pub fn check_isolated_asset_debt(&mut self, token_id: &[u8], expected_debt: u64) {
+        self.b_mock
+            .execute_query(&self.lending_pool_wrapper, |sc| {
+                assert_eq!(
+                    sc.isolated_asset_debt(&managed_token_id!(token_id)).get(),
+                    managed_biguint!(expected_debt),
+                    "Isolated asset debt doesn't match!"
+                );
+            })
+            .assert_ok();
+    }
+
+    pub fn check_isolated_account_debt(
+        &mut self,
+        account_nonce: u64,
+        token_id: &[u8],
+        expected_debt: u64,
+    ) {
+        self.b_mock
+            .execute_query(&self.lending_pool_wrapper, |sc| {
+                assert_eq!(
+                    sc.isolated_account_debt(account_nonce, &managed_token_id!(token_id))
+                        .get(),
+                    managed_biguint!(expected_debt),
+                    "Isolated account debt doesn't match!"
+                );
+            })
+            .assert_ok();
+    }
+
+    pub fn set_e_mode_category(
+        &mut self,
+        category_id: u8,
+        loan_to_value: u64,
+        liquidation_threshold: u64,
+        liquidation_bonus: u64,
+    ) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.risk_admin_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_e_mode_category(
+                    category_id,
+                    managed_biguint!(loan_to_value),
+                    managed_biguint!(liquidation_threshold),
+                    managed_biguint!(liquidation_bonus),
+                );
+            },
+        )
+    }
+
+    pub fn set_asset_e_mode_category(&mut self, token_id: &[u8], category_id: u8) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.risk_admin_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_asset_e_mode_category(managed_token_id!(token_id), category_id);
+            },
+        )
+    }
+
+    pub fn set_account_e_mode_category(
+        &mut self,
+        user_addr: &Address,
+        account_nonce: u64,
+        category_id: u8,
+    ) -> TxResult {
+        self.b_mock.execute_esdt_transfer(
+            user_addr,
+            &self.lending_pool_wrapper,
+            ACCOUNT_TOKEN,
+            account_nonce,
+            &rust_biguint!(1),
+            |sc| {
+                sc.set_account_e_mode_category(category_id);
+            },
+        )
+    }
+
+    pub fn try_liquidate(
+        &mut self,
+        liquidator_addr: &Address,
+        liquidatee_nonce: u64,
+        token_id: &[u8],
+        amount: u64,
+        threshold: u64,
+    ) -> TxResult {
+        self.b_mock.execute_esdt_transfer(
+            liquidator_addr,
+            &self.lending_pool_wrapper,
+            token_id,
+            0,
+            &rust_biguint!(amount),
+            |sc| {
+                sc.liquidate(
+                    liquidatee_nonce,
+                    managed_biguint!(threshold),
+                    managed_token_id!(token_id),
+                );
+            },
+        )
+    }
+
+    /* Checks the getMarkets entry of an asset */
+    pub fn check_market(
+        &mut self,
+        token_id: &[u8],
+        expected_reserves: u64,
+        expected_total_borrow: u64,
+        expected_loan_to_value: u64,
+        expected_price: Option<u64>,
+        expected_supply_cap: u64,
+    ) {
+        let pool_address = self
+            .get_liquidity_pool_wrapper(token_id)
+            .address_ref()
+            .clone();
+
+        self.b_mock
+            .execute_query(&self.lending_pool_wrapper, |sc| {
+                let market = sc
+                    .get_markets()
+                    .into_iter()
+                    .find(|market| market.asset_id == managed_token_id!(token_id))
+                    .expect("Market is not listed!");
+
+                assert_eq!(market.pool_address, managed_address!(&pool_address));
+                assert_eq!(
+                    market.reserves,
+                    managed_biguint!(expected_reserves),
+                    "Market reserves don't match!"
+                );
+                assert_eq!(
+                    market.total_borrow,
+                    managed_biguint!(expected_total_borrow),
+                    "Market total borrow doesn't match!"
+                );
+                assert_eq!(
+                    market.loan_to_value,
+                    managed_biguint!(expected_loan_to_value),
+                    "Market loan to value doesn't match!"
+                );
+                assert_eq!(
+                    market.price,
+                    expected_price.map(|price| managed_biguint!(price)),
+                    "Market price doesn't match!"
+                );
+                assert_eq!(
+                    market.supply_cap,
+                    managed_biguint!(expected_supply_cap),
+                    "Market supply cap doesn't match!"
+                );
+            })
+            .assert_ok();
+    }
+
+    pub fn set_pool_params(
+        &mut self,
+        token_id: &[u8],
+        r_base: u64,
+        r_slope1: u64,
+        r_slope2: u64,
+        u_optimal: u64,
+        reserve_factor: u64,
+    ) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.owner_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_pool_params(
+                    managed_token_id!(token_id),
+                    managed_biguint!(r_base),
+                    managed_biguint!(r_slope1),
+                    managed_biguint!(r_slope2),
+                    managed_biguint!(u_optimal),
+                    managed_biguint!(reserve_factor),
+                );
+            },
+        )
+    }
+
+    pub fn set_liquidation_threshold(
+        &mut self,
+        token_id: &[u8],
+        liquidation_threshold: u64,
+    ) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.risk_admin_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_liquidation_threshold(
+                    managed_token_id!(token_id),
+                    managed_biguint!(liquidation_threshold),
+                );
+            },
+        )
+    }
+
+    pub fn set_interest_rate_model<ModelBuilder>(
+        &mut self,
+        token_id: &[u8],
+        model_builder: ModelBuilder,
+    ) -> TxResult
+    where
+        ModelBuilder: Fn() -> InterestRateModel<DebugApi>,
+    {
+        self.b_mock.execute_tx(
+            &self.owner_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_interest_rate_model(managed_token_id!(token_id), model_builder());
+            },
+        )
+    }
+
+    /* Checks (utilisation, expected borrow rate) points against the pool's current model */
+    pub fn check_borrow_rate_curve(&mut self, token_id: &[u8], rate_curve: &[(u64, u64)]) {
+        let liquidity_pool_wrapper = match token_id {
+            USDC_TOKEN_ID => &self.liquidity_pool_usdc_wrapper,
+            EGLD_TOKEN_ID => &self.liquidity_pool_egld_wrapper,
+            _ => todo!(),
+        };
+
+        self.b_mock
+            .execute_query(liquidity_pool_wrapper, |sc| {
+                let model = sc.interest_rate_model().get();
+                let pool_params = sc.pool_params().get();
+
+                for (u_current, expected_rate) in rate_curve {
+                    let actual_rate =
+                        sc.compute_borrow_rate(&model, &pool_params, &managed_biguint!(*u_current));
+                    assert_eq!(
+                        actual_rate,
+                        managed_biguint!(*expected_rate),
+                        "Borrow rate doesn't match at utilisation {}",
+                        u_current
+                    );
+                }
+            })
+            .assert_ok();
+    }
+
+    pub fn set_pool_utilisation(&mut self, token_id: &[u8], reserves: u64, borrowed_amount: u64) {
+        let liquidity_pool_wrapper = match token_id {
+            USDC_TOKEN_ID => &self.liquidity_pool_usdc_wrapper,
+            EGLD_TOKEN_ID => &self.liquidity_pool_egld_wrapper,
+            _ => todo!(),
+        };
+
+        self.b_mock
+            .execute_tx(
+                &self.owner_addr,
+                liquidity_pool_wrapper,
+                &rust_biguint!(0),
+                |sc| {
+                    sc.reserves().set(managed_biguint!(reserves));
+                    sc.borrowed_amount().set(managed_biguint!(borrowed_amount));
+                    sc.last_capital_utilisation()
+                        .set(sc.get_capital_utilisation());
+                },
+            )
+            .assert_ok();
+    }
+
+    pub fn update_interest_indexes(&mut self, token_id: &[u8]) {
+        let liquidity_pool_wrapper = match token_id {
+            USDC_TOKEN_ID => &self.liquidity_pool_usdc_wrapper,
+            EGLD_TOKEN_ID => &self.liquidity_pool_egld_wrapper,
+            _ => todo!(),
+        };
+
+        self.b_mock
+            .execute_tx(
+                &self.owner_addr,
+                liquidity_pool_wrapper,
+                &rust_biguint!(0),
+                |sc| {
+                    sc.update_interest_indexes();
+                },
+            )
+            .assert_ok();
+    }
+
+    pub fn check_adaptive_rate_state(
+        &mut self,
+        token_id: &[u8],
+        expected_base_rate: u64,
+        expected_capital_utilisation: u64,
+    ) {
+        let liquidity_pool_wrapper = match token_id {
+            USDC_TOKEN_ID => &self.liquidity_pool_usdc_wrapper,
+            EGLD_TOKEN_ID => &self.liquidity_pool_egld_wrapper,
+            _ => todo!(),
+        };
+
+        self.b_mock
+            .execute_query(liquidity_pool_wrapper, |sc| {
+                let state = sc.get_adaptive_rate_state();
+                assert_eq!(
+                    state.base_rate,
+                    managed_biguint!(expected_base_rate),
+                    "Adaptive base rate doesn't match!"
+                );
+                assert_eq!(
+                    state.u_current,
+                    managed_biguint!(expected_capital_utilisation),
+                    "Capital utilisation doesn't match!"
+                );
+            })
+            .assert_ok();
+    }
+
+    pub fn get_borrow_rate(&mut self, token_id: &[u8]) -> u64 {
+        let liquidity_pool_wrapper = match token_id {
+            USDC_TOKEN_ID => &self.liquidity_pool_usdc_wrapper,
+            EGLD_TOKEN_ID => &self.liquidity_pool_egld_wrapper,
+            _ => todo!(),
+        };
+
+        let mut borrow_rate = 0;
+        self.b_mock
+            .execute_query(liquidity_pool_wrapper, |sc| {
+                borrow_rate = sc.get_borrow_rate().to_u64().unwrap();
+            })
+            .assert_ok();
+
+        borrow_rate
+    }
+
+    pub fn get_supply_index(&mut self, token_id: &[u8]) -> u64 {
+        let liquidity_pool_wrapper = match token_id {
+            USDC_TOKEN_ID => &self.liquidity_pool_usdc_wrapper,
+            EGLD_TOKEN_ID => &self.liquidity_pool_egld_wrapper,
+            _ => todo!(),
+        };
+
+        let mut supply_index = 0;
+        self.b_mock
+            .execute_query(liquidity_pool_wrapper, |sc| {
+                supply_index = sc.supply_index().get().to_u64().unwrap();
+            })
+            .assert_ok();
+
+        supply_index
+    }
+
+    /* Returns the pool's (borrow APR, borrow APY, supply APR, supply APY) */
+    pub fn get_annual_rates(&mut self, token_id: &[u8]) -> (u64, u64, u64, u64) {
+        let liquidity_pool_wrapper = match token_id {
+            USDC_TOKEN_ID => &self.liquidity_pool_usdc_wrapper,
+            EGLD_TOKEN_ID => &self.liquidity_pool_egld_wrapper,
+            _ => todo!(),
+        };
+
+        let mut annual_rates = (0, 0, 0, 0);
+        self.b_mock
+            .execute_query(liquidity_pool_wrapper, |sc| {
+                annual_rates = (
+                    sc.get_borrow_apr().to_u64().unwrap(),
+                    sc.get_borrow_apy().to_u64().unwrap(),
+                    sc.get_supply_apr().to_u64().unwrap(),
+                    sc.get_supply_apy().to_u64().unwrap(),
+                );
+            })
+            .assert_ok();
+
+        annual_rates
+    }
+
+    pub fn check_pool_indexes(
+        &mut self,
+        token_id: &[u8],
+        expected_borrow_index: u64,
+        expected_liquidation_threshold: u64,
+    ) {
+        let liquidity_pool_wrapper = match token_id {
+            USDC_TOKEN_ID => &self.liquidity_pool_usdc_wrapper,
+            EGLD_TOKEN_ID => &self.liquidity_pool_egld_wrapper,
+            _ => todo!(),
+        };
+
+        self.b_mock
+            .execute_query(liquidity_pool_wrapper, |sc| {
+                assert_eq!(
+                    sc.borrow_index().get(),
+                    managed_biguint!(expected_borrow_index),
+                    "Borrow index in Liquidity Pool doesn't match!"
+                );
+                assert_eq!(
+                    sc.liquidation_threshold().get(),
+                    managed_biguint!(expected_liquidation_threshold),
+                    "Liquidation threshold in Liquidity Pool doesn't match!"
+                );
+            })
+            .assert_ok();
+    }
+
+    pub fn upgrade_liquidity_pool(
+        &mut self,
+        token_id: &[u8],
+        liquidation_threshold: u64,
+    ) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.owner_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.upgrade_liquidity_pool(
+                    managed_token_id!(token_id),
+                    managed_biguint!(R_BASE),
+                    managed_biguint!(R_SLOPE1),
+                    managed_biguint!(R_SLOPE2),
+                    managed_biguint!(U_OPTIMAL),
+                    managed_biguint!(RESERVE_FACTOR),
+                    managed_biguint!(liquidation_threshold),
+                );
+            },
+        )
+    }
+
+    pub fn upgrade_all_liquidity_pools(&mut self) -> TxResult {
+        self.b_mock.execute_tx(
+            &self.owner_addr,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.upgrade_all_liquidity_pools();
+            },
+        )
+    }
+
+    pub fn continue_pools_upgrade(&mut self, caller: &Address) -> TxResult {
+        self.b_mock.execute_tx(
+            caller,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.continue_pools_upgrade();
+            },
+        )
+    }
+
+    pub fn accrue_all(&mut self, caller: &Address) -> TxResult {
+        self.b_mock.execute_tx(
+            caller,
+            &self.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.accrue_all();
+            },
+        )
+    }
+
+    pub fn check_pools_pending_upgrade(&mut self, expected_pending_pools: usize) {
+        self.b_mock
+            .execute_query(&self.lending_pool_wrapper, |sc| {
+                assert_eq!(
+                    sc.pools_pending_upgrade().len(),
+                    expected_pending_pools,
+                    "Pools pending upgrade don't match!"
+                );
+            })
+            .assert_ok();
+    }
+
+    /* Calls init on an already deployed pool, as the VM does on upgrade */
+    pub fn reinit_liquidity_pool(&mut self, token_id: &[u8], pool_asset: &[u8]) -> TxResult {
+        let liquidity_pool_wrapper = match token_id {
+            USDC_TOKEN_ID => &self.liquidity_pool_usdc_wrapper,
+            EGLD_TOKEN_ID => &self.liquidity_pool_egld_wrapper,
+            _ => todo!(),
+        };
+
+        self.b_mock.execute_tx(
+            &self.owner_addr,
+            liquidity_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.init(
+                    managed_token_id!(pool_asset),
+                    managed_biguint!(R_BASE),
+                    managed_biguint!(R_SLOPE1),
+                    managed_biguint!(R_SLOPE2),
+                    managed_biguint!(U_OPTIMAL),
+                    managed_biguint!(RESERVE_FACTOR),
+                    managed_biguint!(LIQ_THRESOLD),
+                );
+            },
+        )
+    }
+
+    /* Brings a pool back to the storage layout it had before versioning */
+    pub fn reset_pool_storage_version(&mut self, token_id: &[u8]) {
+        let liquidity_pool_wrapper = match token_id {
+            USDC_TOKEN_ID => &self.liquidity_pool_usdc_wrapper,
+            EGLD_TOKEN_ID => &self.liquidity_pool_egld_wrapper,
+            _ => todo!(),
+        };
+
+        self.b_mock
+            .execute_tx(
+                &self.owner_addr,
+                liquidity_pool_wrapper,
+                &rust_biguint!(0),
+                |sc| {
+                    sc.storage_version().clear();
+                    sc.interest_rate_model().clear();
+                },
+            )
+            .assert_ok();
+    }
+
+    /* Overwrites the pool indexes, e.g. with the values of additive indexes */
+    pub fn set_pool_indexes(&mut self, token_id: &[u8], supply_index: u64, borrow_index: u64) {
+        let liquidity_pool_wrapper = match token_id {
+            USDC_TOKEN_ID => &self.liquidity_pool_usdc_wrapper,
+            EGLD_TOKEN_ID => &self.liquidity_pool_egld_wrapper,
+            _ => todo!(),
+        };
+
+        self.b_mock
+            .execute_tx(
+                &self.owner_addr,
+                liquidity_pool_wrapper,
+                &rust_biguint!(0),
+                |sc| {
+                    sc.supply_index().set(managed_biguint!(supply_index));
+                    sc.borrow_index().set(managed_biguint!(borrow_index));
+                },
+            )
+            .assert_ok();
+    }
+
+    pub fn check_pool_storage_version(&mut self, token_id: &[u8], expected_version: u32) {
+        let liquidity_pool_wrapper = match token_id {
+            USDC_TOKEN_ID => &self.liquidity_pool_usdc_wrapper,
+            EGLD_TOKEN_ID => &self.liquidity_pool_egld_wrapper,
+            _ => todo!(),
+        };
+
+        self.b_mock
+            .execute_query(liquidity_pool_wrapper, |sc| {
+                assert_eq!(
+                    sc.storage_version().get(),
+                    expected_version,
+                    "Storage version in Liquidity Pool doesn't match!"
+                );
+                assert!(
+                    sc.interest_rate_model().get() == InterestRateModel::Kinked,
+                    "Interest rate model in Liquidity Pool doesn't match!"
+                );
+            })
+            .assert_ok();
+    }
+}
+
+/* Identifiers of the events logged so far by the transaction being executed */
+pub fn emitted_events() -> Vec<Vec<u8>> {
+    TxContextStack::static_peek()
+        .result_borrow_mut()
+        .result_logs
+        .iter()
+        .filter_map(|log| log.topics.first().cloned())
+        .collect()
+}
+
+/* Returns the topics, event name first, and the data of the last event with the given name */
+pub fn emitted_event(event: &[u8]) -> (Vec<Vec<u8>>, Vec<u8>) {
+    TxContextStack::static_peek()
+        .result_borrow_mut()
+        .result_logs
+        .iter()
+        .rev()
+        .find(|log| log.topics.first().map(|topic| topic.as_slice()) == Some(event))
+        .map(|log| (log.topics.clone(), log.data.clone()))
+        .unwrap_or_else(|| panic!("Event {} was not emitted!", String::from_utf8_lossy(event)))
+}
+
+pub fn assert_events_emitted(events: &[Vec<u8>], expected_events: &[&[u8]]) {
+    for expected_event in expected_events {
+        assert!(
+            events
+                .iter()
+                .any(|event| event.as_slice() == *expected_event),
+            "Event {} was not emitted!",
+            String::from_utf8_lossy(expected_event)
+        );
+    }
+}
+
+type LegacyDepositPosition = (
+    TokenIdentifier<DebugApi>,
+    BigUint<DebugApi>,
+    u64,
+    u64,
+    BigUint<DebugApi>,
+);
+
+type StoredDepositPosition = (
+    TokenIdentifier<DebugApi>,
+    BigUint<DebugApi>,
+    u64,
+    u64,
+    BigUint<DebugApi>,
+    bool,
+);
+
+/* Reads the deposit positions of an account with an explicit layout */
+fn raw_deposit_positions<V: TopEncode + TopDecode>(
+    account_nonce: u64,
+) -> MapMapper<DebugApi, TokenIdentifier<DebugApi>, V> {
+    let mut key = StorageKey::new(b"deposit_positions");
+    key.append_item(&account_nonce);
+    MapMapper::new(key)
 }
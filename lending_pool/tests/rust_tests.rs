@@ -1,7 +1,20 @@
 use constants::*;
 
-use multiversx_sc_scenario::rust_biguint;
-use lending_pool_interaction::LendingSetup;
+use lending_pool::{
+    router::RouterModule, storage::LendingStorageModule, utils::LendingUtilsModule, BorrowPosition,
+    DepositPosition, InterestRateModel, LendingPool, BP, DEBT_CEILING_DECIMALS, ROUNDS_PER_YEAR,
+};
+use liquidity_pool::{liq_storage::StorageModule, liq_utils::UtilsModule};
+use multiversx_sc::{
+    codec::{top_encode_to_vec_u8_or_panic, TopDecode},
+    types::{BigUint, TokenIdentifier},
+};
+use multiversx_sc_scenario::{
+    managed_biguint, managed_buffer, managed_token_id, rust_biguint,
+    whitebox::{TxResult, TxTokenTransfer},
+    DebugApi,
+};
+use lending_pool_interaction::{assert_events_emitted, emitted_event, emitted_events, LendingSetup};
 
 pub mod constants;
 pub mod lending_pool_interaction;
@@ -117,92 +130,2550 @@ fn repay_test() {
 }
 
 #[test]
-fn add_collateral_test() {
+fn repay_on_behalf_test() {
     let mut lending_setup = LendingSetup::deploy_lending(
         lending_pool::contract_obj,
         liquidity_pool::contract_obj,
         aggregator_mock::contract_obj,
     );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let payer_addr = lending_setup.second_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&payer_addr, USDC_TOKEN_ID, &rust_biguint!(500));
+
+    lending_setup.add_collateral_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 1_000);
+    lending_setup
+        .set_liquidation_threshold(USDC_TOKEN_ID, 70 * PERCENT)
+        .assert_ok();
+    lending_setup
+        .set_asset_loan_to_value(USDC_TOKEN_ID, 50 * PERCENT)
+        .assert_ok();
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 300)
+        .assert_ok();
+
+    lending_setup
+        .repay_on_behalf(&payer_addr, USDC_TOKEN_ID, account_nonce, 100)
+        .assert_ok();
+    lending_setup.check_borrow_position(account_nonce, USDC_TOKEN_ID, Some(200));
+    lending_setup
+        .b_mock
+        .execute_query(&lending_setup.liquidity_pool_usdc_wrapper, |sc| {
+            assert_eq!(sc.borrowed_amount().get(), managed_biguint!(200));
+        })
+        .assert_ok();
+
+    // the overpayment goes back to the payer and the repaid position is dropped
+    lending_setup
+        .repay_on_behalf(&payer_addr, USDC_TOKEN_ID, account_nonce, 400)
+        .assert_ok();
+    lending_setup.check_borrow_position(account_nonce, USDC_TOKEN_ID, None);
+    lending_setup
+        .b_mock
+        .execute_query(&lending_setup.liquidity_pool_usdc_wrapper, |sc| {
+            assert_eq!(sc.borrowed_amount().get(), managed_biguint!(0));
+        })
+        .assert_ok();
+    lending_setup
+        .b_mock
+        .check_esdt_balance(&payer_addr, USDC_TOKEN_ID, &rust_biguint!(200));
+    lending_setup
+        .b_mock
+        .check_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(300));
+}
 
+#[test]
+fn borrow_on_behalf_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
     let user_addr = lending_setup.first_user_addr.clone();
+    let delegate_addr = lending_setup.second_user_addr.clone();
     let account_nonce = lending_setup.enter_market(&user_addr);
 
     lending_setup
         .b_mock
         .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
 
-    lending_setup.add_collateral(&user_addr, USDC_TOKEN_ID, 0, account_nonce, 500, 500);
-    lending_setup.add_collateral(&user_addr, USDC_TOKEN_ID, 500, account_nonce, 200, 700);
-    lending_setup.add_collateral(&user_addr, USDC_TOKEN_ID, 700, account_nonce, 150, 850);
+    lending_setup.add_collateral_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 1_000);
+    lending_setup
+        .set_liquidation_threshold(USDC_TOKEN_ID, 70 * PERCENT)
+        .assert_ok();
+    lending_setup
+        .set_asset_loan_to_value(USDC_TOKEN_ID, 50 * PERCENT)
+        .assert_ok();
+
+    lending_setup
+        .borrow_on_behalf(&delegate_addr, account_nonce, USDC_TOKEN_ID, 100)
+        .assert_user_error("borrow allowance exceeded");
+
+    lending_setup
+        .approve_borrow_delegation(
+            &user_addr,
+            account_nonce,
+            &delegate_addr,
+            USDC_TOKEN_ID,
+            200,
+        )
+        .assert_ok();
+    lending_setup
+        .borrow_on_behalf(&delegate_addr, account_nonce, USDC_TOKEN_ID, 150)
+        .assert_ok();
+    lending_setup.check_borrow_allowance(account_nonce, &delegate_addr, USDC_TOKEN_ID, 50);
+    lending_setup.check_borrow_position(account_nonce, USDC_TOKEN_ID, Some(150));
+    lending_setup
+        .b_mock
+        .check_esdt_balance(&delegate_addr, USDC_TOKEN_ID, &rust_biguint!(150));
+
+    lending_setup
+        .borrow_on_behalf(&delegate_addr, account_nonce, USDC_TOKEN_ID, 51)
+        .assert_user_error("borrow allowance exceeded");
+
+    // revoking the delegation stops further draws
+    lending_setup
+        .approve_borrow_delegation(&user_addr, account_nonce, &delegate_addr, USDC_TOKEN_ID, 0)
+        .assert_ok();
+    lending_setup
+        .borrow_on_behalf(&delegate_addr, account_nonce, USDC_TOKEN_ID, 1)
+        .assert_user_error("borrow allowance exceeded");
 }
 
 #[test]
-fn remove_collateral_test() {
+fn multi_asset_operations_test() {
     let mut lending_setup = LendingSetup::deploy_lending(
         lending_pool::contract_obj,
         liquidity_pool::contract_obj,
         aggregator_mock::contract_obj,
     );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, EGLD_TOKEN_ID, &rust_biguint!(10));
+    for token_id in [USDC_TOKEN_ID, EGLD_TOKEN_ID] {
+        lending_setup
+            .set_liquidation_threshold(token_id, 70 * PERCENT)
+            .assert_ok();
+        lending_setup
+            .set_asset_loan_to_value(token_id, 50 * PERCENT)
+            .assert_ok();
+    }
+
+    lending_setup
+        .add_collateral_multi(
+            &user_addr,
+            account_nonce,
+            &[(USDC_TOKEN_ID, 1_000), (EGLD_TOKEN_ID, 10)],
+        )
+        .assert_ok();
+    lending_setup.check_deposit_position(account_nonce, USDC_TOKEN_ID, Some(1_000));
+    lending_setup.check_deposit_position(account_nonce, EGLD_TOKEN_ID, Some(10));
+
+    // borrowing power is (1000 * 100 + 10 * 20000) * 50% = 150000 dollars
+    lending_setup
+        .borrow_multi(
+            &user_addr,
+            account_nonce,
+            &[(USDC_TOKEN_ID, 1_000), (EGLD_TOKEN_ID, 3)],
+        )
+        .assert_user_error("Not enough collateral available for this loan!");
+    lending_setup
+        .borrow_multi(
+            &user_addr,
+            account_nonce,
+            &[(USDC_TOKEN_ID, 900), (EGLD_TOKEN_ID, 3)],
+        )
+        .assert_ok();
+    lending_setup.check_borrow_position(account_nonce, USDC_TOKEN_ID, Some(900));
+    lending_setup.check_borrow_position(account_nonce, EGLD_TOKEN_ID, Some(3));
+
+    lending_setup
+        .remove_collateral_multi(&user_addr, account_nonce, &[(EGLD_TOKEN_ID, 1)])
+        .assert_user_error("Not enough collateral available for this loan!");
 
+    lending_setup
+        .repay_multi(
+            &user_addr,
+            account_nonce,
+            &[(USDC_TOKEN_ID, 400), (EGLD_TOKEN_ID, 2)],
+        )
+        .assert_ok();
+    lending_setup.check_borrow_position(account_nonce, USDC_TOKEN_ID, Some(500));
+    lending_setup.check_borrow_position(account_nonce, EGLD_TOKEN_ID, Some(1));
+
+    // (800 * 100 + 7 * 20000) * 50% still covers the remaining 70000 dollars of debt
+    lending_setup
+        .remove_collateral_multi(
+            &user_addr,
+            account_nonce,
+            &[(USDC_TOKEN_ID, 200), (EGLD_TOKEN_ID, 3)],
+        )
+        .assert_ok();
+    lending_setup
+        .b_mock
+        .check_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(700));
+    lending_setup
+        .b_mock
+        .check_esdt_balance(&user_addr, EGLD_TOKEN_ID, &rust_biguint!(4));
+}
+
+#[test]
+fn borrowing_power_boundary_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
     let user_addr = lending_setup.first_user_addr.clone();
+    let supplier_addr = lending_setup.second_user_addr.clone();
     let account_nonce = lending_setup.enter_market(&user_addr);
+    let supplier_nonce = lending_setup.enter_market(&supplier_addr);
 
     lending_setup
         .b_mock
         .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, EGLD_TOKEN_ID, &rust_biguint!(1));
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&supplier_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+    for token_id in [USDC_TOKEN_ID, EGLD_TOKEN_ID] {
+        lending_setup
+            .set_liquidation_threshold(token_id, 70 * PERCENT)
+            .assert_ok();
+    }
+    lending_setup
+        .set_asset_loan_to_value(USDC_TOKEN_ID, 50 * PERCENT)
+        .assert_ok();
+    lending_setup
+        .set_asset_loan_to_value(EGLD_TOKEN_ID, 25 * PERCENT)
+        .assert_ok();
 
-    lending_setup.add_collateral(&user_addr, USDC_TOKEN_ID, 0, account_nonce, 700, 700);
-    lending_setup.remove_collateral(&user_addr, USDC_TOKEN_ID, 700, account_nonce, 300, 400);
-    lending_setup.add_collateral(&user_addr, USDC_TOKEN_ID, 400, account_nonce, 150, 550);
+    lending_setup.add_collateral_through_lending(
+        &supplier_addr,
+        USDC_TOKEN_ID,
+        supplier_nonce,
+        1_000,
+    );
+    lending_setup.add_collateral_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 1_000);
+    lending_setup.add_collateral_through_lending(&user_addr, EGLD_TOKEN_ID, account_nonce, 1);
+
+    // each collateral counts at its own loan to value: 1000 * 50% + 200 * 25% = 550 USDC
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 551)
+        .assert_user_error("Not enough collateral available for this loan!");
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 550)
+        .assert_ok();
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 1)
+        .assert_user_error("Not enough collateral available for this loan!");
 }
 
 #[test]
-fn liquidate_test() {
+fn exit_market_with_positions_test() {
     let mut lending_setup = LendingSetup::deploy_lending(
         lending_pool::contract_obj,
         liquidity_pool::contract_obj,
         aggregator_mock::contract_obj,
     );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
 
-    let liquidatee_user = lending_setup.first_user_addr.clone();
-    let liquidator_user = lending_setup.second_user_addr.clone();
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+    lending_setup
+        .set_liquidation_threshold(USDC_TOKEN_ID, 70 * PERCENT)
+        .assert_ok();
+    lending_setup
+        .set_asset_loan_to_value(USDC_TOKEN_ID, 50 * PERCENT)
+        .assert_ok();
 
-    let liquidatee_account_nonce = lending_setup.enter_market(&liquidatee_user);
+    lending_setup.add_collateral_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 1_000);
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 100)
+        .assert_ok();
+    lending_setup
+        .try_exit_market(&user_addr, account_nonce)
+        .assert_user_error("account has outstanding debt");
+
+    lending_setup
+        .repay_on_behalf(&user_addr, USDC_TOKEN_ID, account_nonce, 100)
+        .assert_ok();
+    lending_setup.check_borrow_position(account_nonce, USDC_TOKEN_ID, None);
 
+    lending_setup
+        .remove_collateral_multi(&user_addr, account_nonce, &[(USDC_TOKEN_ID, 200)])
+        .assert_ok();
+    lending_setup.check_deposit_position(account_nonce, USDC_TOKEN_ID, Some(800));
+
+    // the leftover collateral is returned on exit
+    lending_setup.exit_market(&user_addr, account_nonce);
+    lending_setup.check_deposit_position(account_nonce, USDC_TOKEN_ID, None);
     lending_setup
         .b_mock
-        .set_esdt_balance(&liquidatee_user, USDC_TOKEN_ID, &rust_biguint!(1_000));
+        .check_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+}
 
-    lending_setup.add_collateral(
-        &liquidatee_user,
+#[test]
+fn accrue_account_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let other_user_addr = lending_setup.second_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+    let other_account_nonce = lending_setup.enter_market(&other_user_addr);
+
+    lending_setup
+        .set_liquidation_threshold(USDC_TOKEN_ID, 70 * PERCENT)
+        .assert_ok();
+    lending_setup
+        .set_asset_loan_to_value(USDC_TOKEN_ID, 50 * PERCENT)
+        .assert_ok();
+    lending_setup
+        .set_interest_rate_model(USDC_TOKEN_ID, || InterestRateModel::Fixed {
+            rate: managed_biguint!(PERCENT),
+        })
+        .assert_ok();
+
+    // two accounts with identical positions
+    for (addr, nonce) in [
+        (&user_addr, account_nonce),
+        (&other_user_addr, other_account_nonce),
+    ] {
+        lending_setup
+            .b_mock
+            .set_esdt_balance(addr, USDC_TOKEN_ID, &rust_biguint!(1_000_000));
+        lending_setup.add_collateral_through_lending(addr, USDC_TOKEN_ID, nonce, 1_000_000);
+        lending_setup
+            .borrow_through_lending(addr, USDC_TOKEN_ID, nonce, 400_000)
+            .assert_ok();
+    }
+
+    lending_setup.b_mock.set_block_round(10);
+    lending_setup
+        .accrue_account(&user_addr, account_nonce)
+        .assert_ok();
+
+    let mut indexes = (0, 0);
+    lending_setup
+        .b_mock
+        .execute_query(&lending_setup.liquidity_pool_usdc_wrapper, |sc| {
+            indexes = (
+                sc.supply_index().get().to_u64().unwrap(),
+                sc.borrow_index().get().to_u64().unwrap(),
+            );
+        })
+        .assert_ok();
+    let (supply_index, borrow_index) = indexes;
+    assert!(supply_index > BP && borrow_index > BP);
+
+    // positions now carry the accrued amounts, measured from the current indexes
+    let accrued = |amount: u64, index: u64| {
+        amount + (amount as u128 * (index - BP) as u128 / BP as u128) as u64
+    };
+    lending_setup.check_deposit_position(
+        account_nonce,
         USDC_TOKEN_ID,
-        0,
-        liquidatee_account_nonce,
-        1000,
-        1000,
+        Some(accrued(1_000_000, supply_index)),
     );
-    lending_setup.borrow(
-        &liquidatee_user,
+    lending_setup.check_borrow_position(
+        account_nonce,
         USDC_TOKEN_ID,
-        0,
-        liquidatee_account_nonce,
-        600,
-        400,
-        600,
+        Some(accrued(400_000, borrow_index)),
     );
+    lending_setup
+        .b_mock
+        .execute_query(&lending_setup.lending_pool_wrapper, |sc| {
+            let position = sc
+                .borrow_positions(account_nonce)
+                .get(&managed_token_id!(USDC_TOKEN_ID))
+                .unwrap();
+            assert_eq!(
+                position.initial_borrow_index,
+                managed_biguint!(borrow_index)
+            );
+            assert_eq!(position.round, 10);
+        })
+        .assert_ok();
+
+    // accruing twice ends where accruing once does, up to rounding, whoever accrues
+    lending_setup.b_mock.set_block_round(20);
+    lending_setup
+        .accrue_account(&other_user_addr, account_nonce)
+        .assert_ok();
+    lending_setup
+        .accrue_account(&other_user_addr, other_account_nonce)
+        .assert_ok();
 
+    let mut amounts = Vec::new();
     lending_setup
         .b_mock
-        .set_esdt_balance(&liquidator_user, USDC_TOKEN_ID, &rust_biguint!(300));
+        .execute_query(&lending_setup.lending_pool_wrapper, |sc| {
+            let token_id = managed_token_id!(USDC_TOKEN_ID);
+            for nonce in [account_nonce, other_account_nonce] {
+                amounts.push((
+                    sc.deposit_positions(nonce)
+                        .get(&token_id)
+                        .unwrap()
+                        .amount
+                        .to_u64()
+                        .unwrap(),
+                    sc.borrow_positions(nonce)
+                        .get(&token_id)
+                        .unwrap()
+                        .amount
+                        .to_u64()
+                        .unwrap(),
+                ));
+            }
+        })
+        .assert_ok();
+    let (deposit, borrow) = amounts[0];
+    let (other_deposit, other_borrow) = amounts[1];
+    assert!(deposit.abs_diff(other_deposit) <= 1);
+    assert!(borrow.abs_diff(other_borrow) <= 1);
+    assert!(borrow > accrued(400_000, borrow_index));
 
-    lending_setup.liquidate(
-        &liquidator_user,
-        &liquidatee_user,
-        liquidatee_account_nonce,
-        300,
-        315,
-        85,
+    lending_setup
+        .accrue_account(&user_addr, other_account_nonce + 1)
+        .assert_user_error("Account not in Lending Protocol!");
+}
+
+#[test]
+fn supply_only_deposit_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, EGLD_TOKEN_ID, &rust_biguint!(10));
+    for token_id in [USDC_TOKEN_ID, EGLD_TOKEN_ID] {
+        lending_setup
+            .set_liquidation_threshold(token_id, 70 * PERCENT)
+            .assert_ok();
+        lending_setup
+            .set_asset_loan_to_value(token_id, 50 * PERCENT)
+            .assert_ok();
+    }
+
+    lending_setup
+        .add_collateral_multi(
+            &user_addr,
+            account_nonce,
+            &[(USDC_TOKEN_ID, 1_000), (EGLD_TOKEN_ID, 10)],
+        )
+        .assert_ok();
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 900)
+        .assert_ok();
+
+    // without the EGLD deposit the borrowing power drops to 1000 * 100 * 50%
+    lending_setup
+        .set_collateral_usage(&user_addr, account_nonce, EGLD_TOKEN_ID, false)
+        .assert_user_error("Not enough collateral available for this loan!");
+    lending_setup
+        .repay_on_behalf(&user_addr, USDC_TOKEN_ID, account_nonce, 500)
+        .assert_ok();
+    lending_setup
+        .set_collateral_usage(&user_addr, account_nonce, EGLD_TOKEN_ID, false)
+        .assert_ok();
+
+    lending_setup
+        .b_mock
+        .execute_query(&lending_setup.lending_pool_wrapper, |sc| {
+            assert_eq!(
+                sc.get_total_collateral_in_dollars(account_nonce),
+                managed_biguint!(1_000 * USDC_PRICE_IN_DOLLARS)
+            );
+        })
+        .assert_ok();
+    assert_eq!(
+        lending_setup.get_liquidation_candidates(1, 1, 2 * BP),
+        vec![(
+            account_nonce,
+            175 * PERCENT,
+            1_000 * USDC_PRICE_IN_DOLLARS,
+            400 * USDC_PRICE_IN_DOLLARS
+        )]
+    );
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 200)
+        .assert_user_error("Not enough collateral available for this loan!");
+
+    lending_setup
+        .set_collateral_usage(&user_addr, account_nonce, EGLD_TOKEN_ID, true)
+        .assert_ok();
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 200)
+        .assert_ok();
+    lending_setup
+        .set_collateral_usage(&user_addr, account_nonce, MEX_TOKEN_ID, false)
+        .assert_user_error("Tokens MEX-123456 are not available for this account");
+}
+
+#[test]
+fn asset_flags_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, EGLD_TOKEN_ID, &rust_biguint!(10));
+    lending_setup
+        .set_liquidation_threshold(USDC_TOKEN_ID, 70 * PERCENT)
+        .assert_ok();
+    lending_setup
+        .set_asset_loan_to_value(USDC_TOKEN_ID, 50 * PERCENT)
+        .assert_ok();
+
+    lending_setup.check_market_flags(USDC_TOKEN_ID, true, true, false);
+    lending_setup
+        .set_asset_can_borrow(MEX_TOKEN_ID, false)
+        .assert_user_error("no pool found for this asset");
+    lending_setup
+        .set_asset_can_borrow(USDC_TOKEN_ID, false)
+        .assert_ok();
+    // EGLD has no loan to value, which is fine for an asset that is never collateral
+    lending_setup
+        .set_asset_can_be_collateral(EGLD_TOKEN_ID, false)
+        .assert_ok();
+    lending_setup.check_market_flags(USDC_TOKEN_ID, false, true, false);
+    lending_setup.check_market_flags(EGLD_TOKEN_ID, true, false, false);
+
+    lending_setup
+        .add_collateral_multi(
+            &user_addr,
+            account_nonce,
+            &[(USDC_TOKEN_ID, 1_000), (EGLD_TOKEN_ID, 10)],
+        )
+        .assert_ok();
+    lending_setup
+        .b_mock
+        .execute_query(&lending_setup.lending_pool_wrapper, |sc| {
+            assert_eq!(
+                sc.get_total_collateral_in_dollars(account_nonce),
+                managed_biguint!(1_000 * USDC_PRICE_IN_DOLLARS)
+            );
+        })
+        .assert_ok();
+
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 1)
+        .assert_user_error("asset can not be borrowed");
+    lending_setup
+        .borrow_through_lending(&user_addr, EGLD_TOKEN_ID, account_nonce, 2)
+        .assert_ok();
+    // only the USDC deposit backs the debt: 1000 * 100 * 50%
+    lending_setup
+        .borrow_through_lending(&user_addr, EGLD_TOKEN_ID, account_nonce, 1)
+        .assert_user_error("Not enough collateral available for this loan!");
+
+    lending_setup
+        .set_collateral_usage(&user_addr, account_nonce, EGLD_TOKEN_ID, false)
+        .assert_ok();
+    lending_setup
+        .set_collateral_usage(&user_addr, account_nonce, EGLD_TOKEN_ID, true)
+        .assert_user_error("asset can not be used as collateral");
+
+    lending_setup
+        .set_asset_can_borrow(USDC_TOKEN_ID, true)
+        .assert_ok();
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 100)
+        .assert_ok();
+}
+
+#[test]
+fn siloed_borrowing_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
     );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, EGLD_TOKEN_ID, &rust_biguint!(10));
+    for token_id in [USDC_TOKEN_ID, EGLD_TOKEN_ID] {
+        lending_setup
+            .set_liquidation_threshold(token_id, 70 * PERCENT)
+            .assert_ok();
+        lending_setup
+            .set_asset_loan_to_value(token_id, 50 * PERCENT)
+            .assert_ok();
+    }
+
+    lending_setup
+        .set_asset_siloed(MEX_TOKEN_ID, true)
+        .assert_user_error("no pool found for this asset");
+    lending_setup
+        .set_asset_siloed(EGLD_TOKEN_ID, true)
+        .assert_ok();
+    lending_setup.check_market_flags(EGLD_TOKEN_ID, true, true, true);
+    lending_setup.check_market_flags(USDC_TOKEN_ID, true, true, false);
+
+    lending_setup
+        .add_collateral_multi(
+            &user_addr,
+            account_nonce,
+            &[(USDC_TOKEN_ID, 1_000), (EGLD_TOKEN_ID, 10)],
+        )
+        .assert_ok();
+    lending_setup
+        .borrow_through_lending(&user_addr, EGLD_TOKEN_ID, account_nonce, 1)
+        .assert_ok();
+    lending_setup
+        .borrow_through_lending(&user_addr, EGLD_TOKEN_ID, account_nonce, 1)
+        .assert_ok();
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 100)
+        .assert_user_error("siloed asset cannot be borrowed with other assets");
+
+    // once the siloed debt is gone, other assets can be borrowed, but not next to it
+    lending_setup
+        .repay_on_behalf(&user_addr, EGLD_TOKEN_ID, account_nonce, 2)
+        .assert_ok();
+    lending_setup.check_borrow_position(account_nonce, EGLD_TOKEN_ID, None);
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 100)
+        .assert_ok();
+    lending_setup
+        .borrow_through_lending(&user_addr, EGLD_TOKEN_ID, account_nonce, 1)
+        .assert_user_error("siloed asset cannot be borrowed with other assets");
+    lending_setup
+        .borrow_multi(
+            &user_addr,
+            account_nonce,
+            &[(USDC_TOKEN_ID, 100), (EGLD_TOKEN_ID, 1)],
+        )
+        .assert_user_error("siloed asset cannot be borrowed with other assets");
+}
+
+#[test]
+fn deposit_position_decodes_without_collateral_flag_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+
+    lending_setup
+        .b_mock
+        .execute_query(&lending_setup.lending_pool_wrapper, |_sc| {
+            // layout of the positions stored before the collateral flag
+            let legacy_position: (
+                TokenIdentifier<DebugApi>,
+                BigUint<DebugApi>,
+                u64,
+                u64,
+                BigUint<DebugApi>,
+            ) = (
+                managed_token_id!(USDC_TOKEN_ID),
+                managed_biguint!(1_000),
+                1u64,
+                5u64,
+                managed_biguint!(BP),
+            );
+            let encoded = top_encode_to_vec_u8_or_panic(&legacy_position);
+            let position = DepositPosition::<DebugApi>::top_decode(&encoded[..]).unwrap();
+            assert_eq!(position.amount, managed_biguint!(1_000));
+            assert_eq!(position.round, 5);
+            assert!(position.is_collateral);
+
+            let mut supply_only = position;
+            supply_only.is_collateral = false;
+            let encoded = top_encode_to_vec_u8_or_panic(&supply_only);
+            let decoded = DepositPosition::<DebugApi>::top_decode(&encoded[..]).unwrap();
+            assert!(!decoded.is_collateral);
+
+            let mut too_long = encoded;
+            too_long.push(0);
+            assert!(DepositPosition::<DebugApi>::top_decode(&too_long[..]).is_err());
+        })
+        .assert_ok();
+}
+
+#[test]
+fn add_collateral_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+
+    let user_addr = lending_setup.first_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+
+    lending_setup.add_collateral(&user_addr, USDC_TOKEN_ID, 0, account_nonce, 500, 500);
+    lending_setup.add_collateral(&user_addr, USDC_TOKEN_ID, 500, account_nonce, 200, 700);
+    lending_setup.add_collateral(&user_addr, USDC_TOKEN_ID, 700, account_nonce, 150, 850);
+}
+
+#[test]
+fn add_collateral_on_behalf_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let supplier_addr = lending_setup.second_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(500));
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&supplier_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+
+    lending_setup.add_collateral_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 500);
+    lending_setup
+        .add_collateral_on_behalf(&supplier_addr, USDC_TOKEN_ID, account_nonce, 300)
+        .assert_ok();
+    lending_setup.check_deposit_position(account_nonce, USDC_TOKEN_ID, Some(800));
+    lending_setup
+        .b_mock
+        .execute_query(&lending_setup.liquidity_pool_usdc_wrapper, |sc| {
+            assert_eq!(sc.reserves().get(), managed_biguint!(800));
+        })
+        .assert_ok();
+
+    lending_setup
+        .add_collateral_on_behalf(&supplier_addr, USDC_TOKEN_ID, account_nonce + 1, 300)
+        .assert_user_error("Account not in Lending Protocol!");
+    // a deposit on behalf never opens a collateral position, so it cannot isolate the account
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&supplier_addr, EGLD_TOKEN_ID, &rust_biguint!(1));
+    lending_setup
+        .set_asset_isolated(EGLD_TOKEN_ID, true)
+        .assert_ok();
+    lending_setup
+        .add_collateral_on_behalf(&supplier_addr, EGLD_TOKEN_ID, account_nonce, 1)
+        .assert_ok();
+    lending_setup.check_stored_collateral_flag(account_nonce, EGLD_TOKEN_ID, false);
+    lending_setup.check_stored_collateral_flag(account_nonce, USDC_TOKEN_ID, true);
+    lending_setup
+        .add_collateral_on_behalf(&supplier_addr, USDC_TOKEN_ID, account_nonce, 100)
+        .assert_ok();
+}
+
+#[test]
+fn remove_collateral_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+
+    let user_addr = lending_setup.first_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+
+    lending_setup.add_collateral(&user_addr, USDC_TOKEN_ID, 0, account_nonce, 700, 700);
+    lending_setup.remove_collateral(&user_addr, USDC_TOKEN_ID, 700, account_nonce, 300, 400);
+    lending_setup.add_collateral(&user_addr, USDC_TOKEN_ID, 400, account_nonce, 150, 550);
+}
+
+#[test]
+fn liquidate_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+
+    let liquidatee_user = lending_setup.first_user_addr.clone();
+    let liquidator_user = lending_setup.second_user_addr.clone();
+
+    let liquidatee_account_nonce = lending_setup.enter_market(&liquidatee_user);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&liquidatee_user, USDC_TOKEN_ID, &rust_biguint!(1_000));
+
+    lending_setup.add_collateral(
+        &liquidatee_user,
+        USDC_TOKEN_ID,
+        0,
+        liquidatee_account_nonce,
+        1000,
+        1000,
+    );
+    lending_setup.borrow(
+        &liquidatee_user,
+        USDC_TOKEN_ID,
+        0,
+        liquidatee_account_nonce,
+        600,
+        400,
+        600,
+    );
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&liquidator_user, USDC_TOKEN_ID, &rust_biguint!(300));
+
+    lending_setup.liquidate(
+        &liquidator_user,
+        &liquidatee_user,
+        liquidatee_account_nonce,
+        300,
+        315,
+        385,
+    );
+}
+
+#[test]
+fn kinked_rate_curve_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+
+    // 1% base, 4% up to 80% utilisation, then 100% more up to full utilisation
+    lending_setup
+        .set_pool_params(
+            USDC_TOKEN_ID,
+            PERCENT,
+            4 * PERCENT,
+            100 * PERCENT,
+            80 * PERCENT,
+            RESERVE_FACTOR,
+        )
+        .assert_ok();
+    lending_setup.check_borrow_rate_curve(
+        USDC_TOKEN_ID,
+        &[
+            (0, PERCENT),
+            (40 * PERCENT, 3 * PERCENT),
+            (80 * PERCENT, 5 * PERCENT),
+            (90 * PERCENT, 55 * PERCENT),
+            (100 * PERCENT, 105 * PERCENT),
+        ],
+    );
+}
+
+#[test]
+fn jump_rate_curve_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+
+    lending_setup
+        .set_pool_params(
+            USDC_TOKEN_ID,
+            PERCENT,
+            4 * PERCENT,
+            100 * PERCENT,
+            80 * PERCENT,
+            RESERVE_FACTOR,
+        )
+        .assert_ok();
+    lending_setup
+        .set_interest_rate_model(USDC_TOKEN_ID, || InterestRateModel::JumpRate {
+            u_jump: managed_biguint!(90 * PERCENT),
+            r_slope3: managed_biguint!(300 * PERCENT),
+        })
+        .assert_ok();
+    lending_setup.check_borrow_rate_curve(
+        USDC_TOKEN_ID,
+        &[
+            (0, PERCENT),
+            (40 * PERCENT, 3 * PERCENT),
+            (80 * PERCENT, 5 * PERCENT),
+            (85 * PERCENT, 55 * PERCENT),
+            (90 * PERCENT, 105 * PERCENT),
+            (95 * PERCENT, 255 * PERCENT),
+            (100 * PERCENT, 405 * PERCENT),
+        ],
+    );
+}
+
+#[test]
+fn fixed_rate_curve_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+
+    lending_setup
+        .set_interest_rate_model(USDC_TOKEN_ID, || InterestRateModel::Fixed {
+            rate: managed_biguint!(2 * PERCENT),
+        })
+        .assert_ok();
+    lending_setup.check_borrow_rate_curve(
+        USDC_TOKEN_ID,
+        &[
+            (0, 2 * PERCENT),
+            (50 * PERCENT, 2 * PERCENT),
+            (100 * PERCENT, 2 * PERCENT),
+        ],
+    );
+
+    // the other pools keep the default kinked model
+    lending_setup.check_borrow_rate_curve(EGLD_TOKEN_ID, &[(0, R_BASE), (U_OPTIMAL, R_SLOPE1)]);
+}
+
+#[test]
+fn annual_rates_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+
+    // 10% a year, accrued every round
+    let rate_per_round = 10 * PERCENT / ROUNDS_PER_YEAR;
+    lending_setup
+        .set_interest_rate_model(USDC_TOKEN_ID, || InterestRateModel::Fixed {
+            rate: managed_biguint!(rate_per_round),
+        })
+        .assert_ok();
+    lending_setup.set_pool_utilisation(USDC_TOKEN_ID, BP / 2, BP / 2);
+
+    let (borrow_apr, borrow_apy, supply_apr, supply_apy) =
+        lending_setup.get_annual_rates(USDC_TOKEN_ID);
+
+    // suppliers earn what the supply index accrues
+    let rounds = 1_000;
+    let initial_supply_index = lending_setup.get_supply_index(USDC_TOKEN_ID);
+    lending_setup.b_mock.set_block_round(rounds);
+    lending_setup.update_interest_indexes(USDC_TOKEN_ID);
+    let supply_rate =
+        (lending_setup.get_supply_index(USDC_TOKEN_ID) - initial_supply_index) / rounds;
+    assert_eq!(supply_rate, rate_per_round / 2);
+
+    assert_eq!(borrow_apr, rate_per_round * ROUNDS_PER_YEAR);
+    assert_eq!(supply_apr, supply_rate * ROUNDS_PER_YEAR);
+
+    // (1 + r)^n - 1, close to e^0.1 - 1 for the borrow side
+    let compounded = |rate: u64| {
+        ((1.0 + rate as f64 / BP as f64).powf(ROUNDS_PER_YEAR as f64) - 1.0) * BP as f64
+    };
+    assert_close(borrow_apy, compounded(rate_per_round));
+    assert_close(supply_apy, compounded(supply_rate));
+    assert!(borrow_apy > borrow_apr && supply_apy > supply_apr);
+}
+
+fn assert_close(actual: u64, expected: f64) {
+    let relative_error = (actual as f64 - expected).abs() / expected;
+    assert!(
+        relative_error < 1e-6,
+        "Expected {} to be close to {}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn invalid_jump_rate_model_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+
+    lending_setup
+        .set_pool_params(
+            USDC_TOKEN_ID,
+            PERCENT,
+            4 * PERCENT,
+            100 * PERCENT,
+            80 * PERCENT,
+            RESERVE_FACTOR,
+        )
+        .assert_ok();
+    lending_setup
+        .set_interest_rate_model(USDC_TOKEN_ID, || InterestRateModel::JumpRate {
+            u_jump: managed_biguint!(70 * PERCENT),
+            r_slope3: managed_biguint!(300 * PERCENT),
+        })
+        .assert_user_error("u_jump must be between u_optimal and BP");
+}
+
+#[test]
+fn adaptive_rate_drift_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+
+    lending_setup
+        .set_pool_params(
+            USDC_TOKEN_ID,
+            PERCENT,
+            4 * PERCENT,
+            100 * PERCENT,
+            80 * PERCENT,
+            RESERVE_FACTOR,
+        )
+        .assert_ok();
+    lending_setup
+        .set_interest_rate_model(USDC_TOKEN_ID, || InterestRateModel::Adaptive {
+            u_target: managed_biguint!(50 * PERCENT),
+            adjustment_speed: managed_biguint!(PERCENT),
+            min_base_rate: managed_biguint!(PERCENT / 2),
+            max_base_rate: managed_biguint!(3 * PERCENT),
+        })
+        .assert_ok();
+    lending_setup.check_adaptive_rate_state(USDC_TOKEN_ID, PERCENT, 0);
+
+    // 60% utilisation: 10% above target drifts the base rate up by 0.1% per round
+    lending_setup.set_pool_utilisation(USDC_TOKEN_ID, 400, 600);
+    lending_setup.b_mock.set_block_round(10);
+    lending_setup.update_interest_indexes(USDC_TOKEN_ID);
+    lending_setup.check_adaptive_rate_state(USDC_TOKEN_ID, 2 * PERCENT, 60 * PERCENT);
+
+    lending_setup.b_mock.set_block_round(100);
+    lending_setup.update_interest_indexes(USDC_TOKEN_ID);
+    lending_setup.check_adaptive_rate_state(USDC_TOKEN_ID, 3 * PERCENT, 60 * PERCENT);
+
+    // 10% utilisation: 40% below target drifts the base rate down by 0.4% per round
+    lending_setup.set_pool_utilisation(USDC_TOKEN_ID, 900, 100);
+    lending_setup.b_mock.set_block_round(105);
+    lending_setup.update_interest_indexes(USDC_TOKEN_ID);
+    lending_setup.check_adaptive_rate_state(USDC_TOKEN_ID, PERCENT, 10 * PERCENT);
+
+    lending_setup.b_mock.set_block_round(200);
+    lending_setup.update_interest_indexes(USDC_TOKEN_ID);
+    lending_setup.check_adaptive_rate_state(USDC_TOKEN_ID, PERCENT / 2, 10 * PERCENT);
+
+    // the curve kinks at the target, on top of the drifted base rate
+    lending_setup.check_borrow_rate_curve(
+        USDC_TOKEN_ID,
+        &[
+            (0, PERCENT),
+            (25 * PERCENT, 3 * PERCENT),
+            (50 * PERCENT, 5 * PERCENT),
+        ],
+    );
+
+    // retuning the controller keeps the rate it drifted to, within the new bounds
+    lending_setup
+        .set_interest_rate_model(USDC_TOKEN_ID, || InterestRateModel::Adaptive {
+            u_target: managed_biguint!(50 * PERCENT),
+            adjustment_speed: managed_biguint!(2 * PERCENT),
+            min_base_rate: managed_biguint!(PERCENT / 2),
+            max_base_rate: managed_biguint!(3 * PERCENT),
+        })
+        .assert_ok();
+    lending_setup.check_adaptive_rate_state(USDC_TOKEN_ID, PERCENT / 2, 10 * PERCENT);
+    lending_setup
+        .set_interest_rate_model(USDC_TOKEN_ID, || InterestRateModel::Adaptive {
+            u_target: managed_biguint!(50 * PERCENT),
+            adjustment_speed: managed_biguint!(2 * PERCENT),
+            min_base_rate: managed_biguint!(2 * PERCENT),
+            max_base_rate: managed_biguint!(4 * PERCENT),
+        })
+        .assert_ok();
+    lending_setup.check_adaptive_rate_state(USDC_TOKEN_ID, 2 * PERCENT, 10 * PERCENT);
+}
+
+#[test]
+fn set_pool_params_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+
+    lending_setup.add_collateral(&user_addr, USDC_TOKEN_ID, 0, account_nonce, 1000, 1000);
+    lending_setup.borrow(&user_addr, USDC_TOKEN_ID, 0, account_nonce, 600, 400, 600);
+
+    let old_borrow_rate = lending_setup.get_borrow_rate(USDC_TOKEN_ID);
+    lending_setup.b_mock.set_block_round(10);
+
+    // the rounds elapsed so far accrue with the old parameters, and the indexes are kept
+    lending_setup
+        .set_pool_params(
+            USDC_TOKEN_ID,
+            PERCENT,
+            4 * PERCENT,
+            100 * PERCENT,
+            80 * PERCENT,
+            RESERVE_FACTOR,
+        )
+        .assert_ok();
+    let borrow_index = BP + 10 * old_borrow_rate;
+    lending_setup.check_pool_indexes(USDC_TOKEN_ID, borrow_index, LIQ_THRESOLD);
+    assert_eq!(
+        lending_setup.get_borrow_rate(USDC_TOKEN_ID),
+        PERCENT + 3 * PERCENT,
+        "Borrow rate doesn't use the new parameters!"
+    );
+
+    lending_setup
+        .set_liquidation_threshold(USDC_TOKEN_ID, 80 * PERCENT)
+        .assert_ok();
+    lending_setup.check_pool_indexes(USDC_TOKEN_ID, borrow_index, 80 * PERCENT);
+}
+
+#[test]
+fn set_invalid_pool_params_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+
+    lending_setup
+        .set_pool_params(
+            USDC_TOKEN_ID,
+            R_BASE,
+            R_SLOPE1,
+            R_SLOPE2,
+            BP,
+            RESERVE_FACTOR,
+        )
+        .assert_user_error("u_optimal must be between 0 and BP");
+    lending_setup
+        .set_pool_params(USDC_TOKEN_ID, R_BASE, R_SLOPE1, R_SLOPE2, U_OPTIMAL, BP + 1)
+        .assert_user_error("reserve_factor must not exceed BP");
+    lending_setup
+        .set_liquidation_threshold(USDC_TOKEN_ID, 0)
+        .assert_user_error("liquidation_threshold must be between 0 and BP");
+}
+
+#[test]
+fn create_liquidity_pool_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+
+    lending_setup
+        .create_liquidity_pool(
+            MEX_TOKEN_ID,
+            R_BASE,
+            R_SLOPE1,
+            R_SLOPE2,
+            BP,
+            RESERVE_FACTOR,
+            LIQ_THRESOLD,
+        )
+        .assert_user_error("u_optimal must be between 0 and BP");
+    lending_setup
+        .create_liquidity_pool(
+            MEX_TOKEN_ID,
+            R_BASE,
+            R_SLOPE1,
+            R_SLOPE2,
+            U_OPTIMAL,
+            BP + 1,
+            LIQ_THRESOLD,
+        )
+        .assert_user_error("reserve_factor must not exceed BP");
+    lending_setup
+        .create_liquidity_pool(
+            MEX_TOKEN_ID,
+            R_BASE,
+            R_SLOPE1,
+            R_SLOPE2,
+            U_OPTIMAL,
+            RESERVE_FACTOR,
+            BP + 1,
+        )
+        .assert_user_error("liquidation_threshold must be between 0 and BP");
+
+    let lending_pool_addr = lending_setup.lending_pool_wrapper.address_ref().clone();
+    lending_setup
+        .b_mock
+        .prepare_deploy_from_sc(&lending_pool_addr, liquidity_pool::contract_obj);
+    lending_setup
+        .create_liquidity_pool(
+            MEX_TOKEN_ID,
+            R_BASE,
+            R_SLOPE1,
+            R_SLOPE2,
+            U_OPTIMAL,
+            RESERVE_FACTOR,
+            LIQ_THRESOLD,
+        )
+        .assert_ok();
+    lending_setup
+        .b_mock
+        .execute_query(&lending_setup.lending_pool_wrapper, |sc| {
+            let asset_config = sc.asset_config(&managed_token_id!(MEX_TOKEN_ID)).get();
+            assert_eq!(asset_config.decimals, 18);
+        })
+        .assert_ok();
+    lending_setup
+        .create_liquidity_pool(
+            MEX_TOKEN_ID,
+            R_BASE,
+            R_SLOPE1,
+            R_SLOPE2,
+            U_OPTIMAL,
+            RESERVE_FACTOR,
+            LIQ_THRESOLD,
+        )
+        .assert_user_error("asset already supported");
+}
+
+#[test]
+fn loan_to_value_below_liquidation_threshold_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+
+    lending_setup
+        .set_asset_loan_to_value(USDC_TOKEN_ID, 0)
+        .assert_user_error("loan_to_value value can not be zero");
+    lending_setup
+        .set_asset_loan_to_value(USDC_TOKEN_ID, LIQ_THRESOLD)
+        .assert_user_error("loan_to_value must be lower than liquidation_threshold");
+    lending_setup
+        .set_asset_loan_to_value(USDC_TOKEN_ID, LIQ_THRESOLD / 2)
+        .assert_ok();
+
+    lending_setup
+        .set_liquidation_threshold(USDC_TOKEN_ID, LIQ_THRESOLD / 2)
+        .assert_user_error("loan_to_value must be lower than liquidation_threshold");
+    lending_setup
+        .set_liquidation_threshold(USDC_TOKEN_ID, LIQ_THRESOLD / 2 + 1)
+        .assert_ok();
+
+    lending_setup
+        .set_asset_liquidation_bonus(USDC_TOKEN_ID, BP)
+        .assert_user_error("liquidation_bonus must be between 0 and BP");
+    lending_setup
+        .set_asset_liquidation_bonus(USDC_TOKEN_ID, BP / 10)
+        .assert_ok();
+}
+
+#[test]
+fn protocol_events_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+
+    let events = lending_setup.add_collateral_through_lending(
+        &user_addr,
+        USDC_TOKEN_ID,
+        account_nonce,
+        1_000,
+    );
+    assert_events_emitted(&events, &[b"add_collateral", b"update_market_state"]);
+
+    let risk_admin_addr = lending_setup.risk_admin_addr.clone();
+    lending_setup
+        .b_mock
+        .execute_tx(
+            &risk_admin_addr,
+            &lending_setup.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_asset_loan_to_value(
+                    managed_token_id!(USDC_TOKEN_ID),
+                    managed_biguint!(LIQ_THRESOLD / 2),
+                );
+                sc.set_liquidation_threshold(
+                    managed_token_id!(USDC_TOKEN_ID),
+                    managed_biguint!(LIQ_THRESOLD),
+                );
+                assert_events_emitted(
+                    &emitted_events(),
+                    &[
+                        b"update_asset_loan_to_value",
+                        b"update_liquidation_threshold",
+                    ],
+                );
+            },
+        )
+        .assert_ok();
+}
+
+#[test]
+fn account_events_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let liquidator_addr = lending_setup.second_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&liquidator_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+    lending_setup
+        .set_liquidation_threshold(USDC_TOKEN_ID, 70 * PERCENT)
+        .assert_ok();
+    lending_setup
+        .set_asset_loan_to_value(USDC_TOKEN_ID, 60 * PERCENT)
+        .assert_ok();
+    lending_setup.add_collateral_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 1_000);
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 500)
+        .assert_ok();
+
+    // 1000 USDC weighted at 45% no longer cover 500 USDC of debt
+    lending_setup
+        .set_asset_loan_to_value(USDC_TOKEN_ID, 40 * PERCENT)
+        .assert_ok();
+    lending_setup
+        .set_liquidation_threshold(USDC_TOKEN_ID, 45 * PERCENT)
+        .assert_ok();
+
+    // the event carries the indexes and the liquidatee's position after the seizure
+    lending_setup
+        .b_mock
+        .execute_esdt_transfer(
+            &liquidator_addr,
+            &lending_setup.lending_pool_wrapper,
+            USDC_TOKEN_ID,
+            0,
+            &rust_biguint!(300),
+            |sc| {
+                sc.liquidate(
+                    account_nonce,
+                    managed_biguint!(BP / 2),
+                    managed_token_id!(USDC_TOKEN_ID),
+                );
+
+                let (topics, data) = emitted_event(b"liquidate");
+                assert_eq!(topics[4], rust_biguint!(300).to_bytes_be());
+                assert_eq!(topics[5], rust_biguint!(315).to_bytes_be());
+                assert_eq!(topics[6], rust_biguint!(BP).to_bytes_be());
+                assert_eq!(topics[7], rust_biguint!(BP).to_bytes_be());
+                let position = DepositPosition::<DebugApi>::top_decode(&data[..]).unwrap();
+                assert_eq!(position.amount, managed_biguint!(685));
+            },
+        )
+        .assert_ok();
+    lending_setup.check_deposit_position(account_nonce, USDC_TOKEN_ID, Some(685));
+    lending_setup.check_borrow_position(account_nonce, USDC_TOKEN_ID, Some(200));
+
+    // an overpayment is refunded, so only the debt counts as repaid
+    lending_setup
+        .b_mock
+        .execute_esdt_transfer(
+            &liquidator_addr,
+            &lending_setup.lending_pool_wrapper,
+            USDC_TOKEN_ID,
+            0,
+            &rust_biguint!(600),
+            |sc| {
+                sc.repay_on_behalf(account_nonce);
+
+                let (topics, _) = emitted_event(b"repay");
+                assert_eq!(topics[4], rust_biguint!(200).to_bytes_be());
+            },
+        )
+        .assert_ok();
+    lending_setup.b_mock.check_esdt_balance(
+        &liquidator_addr,
+        USDC_TOKEN_ID,
+        &rust_biguint!(1_000 - 300 + 315 - 200),
+    );
+}
+
+#[test]
+fn liquidate_accrued_debt_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let liquidator_addr = lending_setup.second_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+    let second_account_nonce = lending_setup.enter_market(&liquidator_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&liquidator_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+    lending_setup
+        .set_liquidation_threshold(USDC_TOKEN_ID, 61 * PERCENT)
+        .assert_ok();
+    lending_setup
+        .set_asset_loan_to_value(USDC_TOKEN_ID, 60 * PERCENT)
+        .assert_ok();
+    lending_setup.add_collateral_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 1_000);
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 600)
+        .assert_ok();
+
+    // about 10% of interest on the debt, of which the weighted collateral earns back 61%
+    let borrow_rate = lending_setup.get_borrow_rate(USDC_TOKEN_ID);
+    let rounds = BP / 10 / borrow_rate;
+    lending_setup.b_mock.set_block_round(rounds);
+    let debt = 600 + (600 * borrow_rate as u128 * rounds as u128 / BP as u128) as u64;
+
+    // the stored positions are still healthy, the accrued ones are not
+    lending_setup
+        .b_mock
+        .execute_esdt_transfer(
+            &liquidator_addr,
+            &lending_setup.lending_pool_wrapper,
+            USDC_TOKEN_ID,
+            0,
+            &rust_biguint!(400),
+            |sc| {
+                sc.liquidate(
+                    account_nonce,
+                    managed_biguint!(BP / 2),
+                    managed_token_id!(USDC_TOKEN_ID),
+                );
+
+                let (topics, _) = emitted_event(b"liquidate");
+                assert_eq!(topics[4], rust_biguint!(400).to_bytes_be());
+                assert_eq!(topics[5], rust_biguint!(420).to_bytes_be());
+            },
+        )
+        .assert_ok();
+    lending_setup.check_borrow_position(account_nonce, USDC_TOKEN_ID, Some(debt - 400));
+    lending_setup.b_mock.check_esdt_balance(
+        &liquidator_addr,
+        USDC_TOKEN_ID,
+        &rust_biguint!(1_000 - 400 + 420),
+    );
+
+    // the liquidation token has to be owed by the liquidatee
+    lending_setup.set_account_positions(
+        second_account_nonce,
+        &[(USDC_TOKEN_ID, 1_000)],
+        &[(EGLD_TOKEN_ID, 4)],
+    );
+    lending_setup
+        .try_liquidate(
+            &liquidator_addr,
+            second_account_nonce,
+            USDC_TOKEN_ID,
+            500,
+            BP / 2,
+        )
+        .assert_user_error("Liquidatee user has no debt in the liquidation token");
+}
+
+#[test]
+fn risk_admin_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let owner_addr = lending_setup.owner_addr.clone();
+
+    lending_setup
+        .b_mock
+        .execute_tx(
+            &owner_addr,
+            &lending_setup.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_asset_supply_cap(managed_token_id!(USDC_TOKEN_ID), managed_biguint!(1));
+            },
+        )
+        .assert_user_error("caller is not the risk admin");
+
+    lending_setup
+        .set_asset_loan_to_value(USDC_TOKEN_ID, LIQ_THRESOLD / 2)
+        .assert_ok();
+    lending_setup
+        .set_asset_borrow_cap(USDC_TOKEN_ID, 500)
+        .assert_ok();
+    lending_setup
+        .set_asset_siloed(USDC_TOKEN_ID, true)
+        .assert_ok();
+    lending_setup
+        .set_asset_oracle(MEX_TOKEN_ID, b"MEX", 18)
+        .assert_user_error("no pool found for this asset");
+    // prices USDC with the feed of EGLD, which makes the override visible
+    lending_setup
+        .b_mock
+        .execute_tx(
+            &lending_setup.risk_admin_addr,
+            &lending_setup.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_asset_oracle(
+                    managed_token_id!(USDC_TOKEN_ID),
+                    managed_buffer!(b"WEGLD"),
+                    6,
+                );
+                assert_events_emitted(&emitted_events(), &[b"update_asset_oracle"]);
+            },
+        )
+        .assert_ok();
+
+    lending_setup
+        .b_mock
+        .execute_query(&lending_setup.lending_pool_wrapper, |sc| {
+            let asset_config = sc.asset_config(&managed_token_id!(USDC_TOKEN_ID)).get();
+            assert_eq!(
+                asset_config.loan_to_value,
+                managed_biguint!(LIQ_THRESOLD / 2)
+            );
+            assert_eq!(
+                asset_config.liquidation_threshold,
+                managed_biguint!(LIQ_THRESOLD)
+            );
+            assert_eq!(asset_config.liquidation_bonus, managed_biguint!(BP / 20));
+            assert_eq!(asset_config.supply_cap, managed_biguint!(0));
+            assert_eq!(asset_config.borrow_cap, managed_biguint!(500));
+            assert!(asset_config.can_borrow && asset_config.can_be_collateral);
+            assert!(asset_config.is_siloed && !asset_config.is_isolated);
+            assert_eq!(asset_config.decimals, 6);
+            assert_eq!(asset_config.oracle_id, managed_buffer!(b"WEGLD"));
+
+            let price_data = sc.get_token_price_data(managed_token_id!(USDC_TOKEN_ID));
+            assert_eq!(price_data.price, managed_biguint!(EGLD_PRICE_IN_DOLLARS));
+        })
+        .assert_ok();
+
+    let new_risk_admin_addr = lending_setup.second_user_addr.clone();
+    lending_setup
+        .set_risk_admin(&new_risk_admin_addr)
+        .assert_ok();
+    lending_setup
+        .set_asset_borrow_cap(USDC_TOKEN_ID, 0)
+        .assert_user_error("caller is not the risk admin");
+}
+
+#[test]
+fn asset_config_migration_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+
+    lending_setup.restore_legacy_asset_params(USDC_TOKEN_ID, LIQ_THRESOLD / 2);
+    lending_setup.restore_legacy_asset_params(EGLD_TOKEN_ID, 0);
+    lending_setup
+        .upgrade_lending_pool(&[(USDC_TOKEN_ID, 6)])
+        .assert_user_error("asset decimals not provided for WEGLD-123456");
+    lending_setup
+        .upgrade_lending_pool(&[(USDC_TOKEN_ID, 6), (EGLD_TOKEN_ID, 18)])
+        .assert_ok();
+
+    lending_setup
+        .b_mock
+        .execute_query(&lending_setup.lending_pool_wrapper, |sc| {
+            assert_eq!(sc.storage_version().get(), 3);
+
+            let usdc_token_id = managed_token_id!(USDC_TOKEN_ID);
+            let asset_config = sc.asset_config(&usdc_token_id).get();
+            assert_eq!(
+                asset_config.loan_to_value,
+                managed_biguint!(LIQ_THRESOLD / 2)
+            );
+            assert_eq!(
+                asset_config.liquidation_threshold,
+                managed_biguint!(LIQ_THRESOLD)
+            );
+            assert_eq!(asset_config.liquidation_bonus, managed_biguint!(BP / 20));
+            assert_eq!(asset_config.decimals, 6);
+            assert_eq!(asset_config.oracle_id, managed_buffer!(b"USDC"));
+            assert!(asset_config.can_borrow && asset_config.can_be_collateral);
+            assert!(sc.legacy_asset_loan_to_value(&usdc_token_id).is_empty());
+            assert!(sc.legacy_asset_liquidation_bonus(&usdc_token_id).is_empty());
+
+            let asset_config = sc.asset_config(&managed_token_id!(EGLD_TOKEN_ID)).get();
+            assert_eq!(asset_config.loan_to_value, managed_biguint!(0));
+            assert_eq!(asset_config.decimals, 18);
+            assert_eq!(asset_config.oracle_id, managed_buffer!(b"WEGLD"));
+        })
+        .assert_ok();
+}
+
+#[test]
+fn deposit_position_migration_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+
+    lending_setup.restore_legacy_deposit_position(account_nonce, USDC_TOKEN_ID, 1_000);
+    lending_setup.upgrade_lending_pool(&[]).assert_ok();
+
+    lending_setup
+        .b_mock
+        .execute_query(&lending_setup.lending_pool_wrapper, |sc| {
+            assert_eq!(sc.storage_version().get(), 3);
+
+            let position = sc
+                .deposit_positions(account_nonce)
+                .get(&managed_token_id!(USDC_TOKEN_ID))
+                .unwrap();
+            assert_eq!(position.amount, managed_biguint!(1_000));
+            assert!(position.is_collateral);
+        })
+        .assert_ok();
+
+    // the upgrade leaves the position as it is, it is rewritten on first touch
+    lending_setup.check_legacy_deposit_position(account_nonce, USDC_TOKEN_ID);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(500));
+    lending_setup.add_collateral_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 500);
+    lending_setup.check_stored_collateral_flag(account_nonce, USDC_TOKEN_ID, true);
+}
+
+#[test]
+fn risk_params_ramp_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let liquidator_addr = lending_setup.second_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&liquidator_addr, USDC_TOKEN_ID, &rust_biguint!(300));
+    lending_setup.b_mock.set_block_timestamp(1_000);
+    for token_id in [USDC_TOKEN_ID, EGLD_TOKEN_ID] {
+        lending_setup
+            .set_liquidation_threshold(token_id, 70 * PERCENT)
+            .assert_ok();
+        lending_setup
+            .set_asset_loan_to_value(token_id, 60 * PERCENT)
+            .assert_ok();
+    }
+
+    lending_setup
+        .ramp_asset_risk_params(USDC_TOKEN_ID, 40 * PERCENT, 50 * PERCENT, 1_000)
+        .assert_user_error("ramp must end in the future");
+    lending_setup
+        .ramp_asset_risk_params(USDC_TOKEN_ID, 50 * PERCENT, 40 * PERCENT, 2_000)
+        .assert_user_error("loan_to_value must be lower than liquidation_threshold");
+
+    lending_setup.add_collateral_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 1_000);
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 550)
+        .assert_ok();
+
+    lending_setup
+        .ramp_asset_risk_params(USDC_TOKEN_ID, 40 * PERCENT, 50 * PERCENT, 2_000)
+        .assert_ok();
+    lending_setup.check_asset_risk_params(
+        USDC_TOKEN_ID,
+        (60 * PERCENT, 70 * PERCENT),
+        (40 * PERCENT, 50 * PERCENT),
+        2_000,
+    );
+
+    // halfway through, 1000 USDC back at most 500 USDC of debt, and 600 before liquidation
+    lending_setup.b_mock.set_block_timestamp(1_500);
+    lending_setup.check_asset_risk_params(
+        USDC_TOKEN_ID,
+        (50 * PERCENT, 60 * PERCENT),
+        (40 * PERCENT, 50 * PERCENT),
+        2_000,
+    );
+    lending_setup.check_market(
+        USDC_TOKEN_ID,
+        450,
+        550,
+        50 * PERCENT,
+        Some(USDC_PRICE_IN_DOLLARS),
+        0,
+    );
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 1)
+        .assert_user_error("Not enough collateral available for this loan!");
+    lending_setup
+        .try_liquidate(&liquidator_addr, account_nonce, USDC_TOKEN_ID, 300, BP / 2)
+        .assert_user_error("health not low enough for liquidation");
+
+    // the threshold reaches 54%, below the 55% of debt
+    lending_setup.b_mock.set_block_timestamp(1_800);
+    lending_setup
+        .try_liquidate(&liquidator_addr, account_nonce, USDC_TOKEN_ID, 300, BP / 2)
+        .assert_ok();
+
+    lending_setup.b_mock.set_block_timestamp(2_500);
+    lending_setup.check_asset_risk_params(
+        USDC_TOKEN_ID,
+        (40 * PERCENT, 50 * PERCENT),
+        (40 * PERCENT, 50 * PERCENT),
+        0,
+    );
+
+    // the pool keeps its threshold while the ramp runs
+    lending_setup.b_mock.set_block_timestamp(3_000);
+    lending_setup
+        .ramp_asset_risk_params(EGLD_TOKEN_ID, 40 * PERCENT, 50 * PERCENT, 4_000)
+        .assert_ok();
+    lending_setup.check_pool_indexes(EGLD_TOKEN_ID, BP, 70 * PERCENT);
+
+    // setting a value directly ends the ramp where it got to, and the pool follows
+    lending_setup.b_mock.set_block_timestamp(3_500);
+    lending_setup
+        .set_asset_loan_to_value(EGLD_TOKEN_ID, 45 * PERCENT)
+        .assert_ok();
+    lending_setup.check_pool_indexes(EGLD_TOKEN_ID, BP, 60 * PERCENT);
+    lending_setup.b_mock.set_block_timestamp(3_800);
+    lending_setup.check_asset_risk_params(
+        EGLD_TOKEN_ID,
+        (45 * PERCENT, 60 * PERCENT),
+        (45 * PERCENT, 60 * PERCENT),
+        0,
+    );
+
+    // so does replacing a ramp, and settling one once finished
+    lending_setup
+        .ramp_asset_risk_params(EGLD_TOKEN_ID, 40 * PERCENT, 50 * PERCENT, 4_800)
+        .assert_ok();
+    lending_setup.b_mock.set_block_timestamp(4_400);
+    lending_setup
+        .ramp_asset_risk_params(EGLD_TOKEN_ID, 40 * PERCENT, 50 * PERCENT, 5_000)
+        .assert_ok();
+    lending_setup.check_pool_indexes(EGLD_TOKEN_ID, BP, 54 * PERCENT);
+    lending_setup
+        .settle_risk_params_ramp(&user_addr, EGLD_TOKEN_ID)
+        .assert_user_error("risk params ramp in progress");
+    lending_setup.b_mock.set_block_timestamp(5_000);
+    lending_setup
+        .settle_risk_params_ramp(&user_addr, EGLD_TOKEN_ID)
+        .assert_ok();
+    lending_setup.check_pool_indexes(EGLD_TOKEN_ID, BP, 50 * PERCENT);
+    lending_setup.check_asset_risk_params(
+        EGLD_TOKEN_ID,
+        (40 * PERCENT, 50 * PERCENT),
+        (40 * PERCENT, 50 * PERCENT),
+        0,
+    );
+}
+
+#[test]
+fn get_markets_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+
+    lending_setup.add_collateral(&user_addr, USDC_TOKEN_ID, 0, account_nonce, 1000, 1000);
+    lending_setup.borrow(&user_addr, USDC_TOKEN_ID, 0, account_nonce, 600, 400, 600);
+    lending_setup
+        .set_asset_loan_to_value(USDC_TOKEN_ID, LIQ_THRESOLD / 2)
+        .assert_ok();
+    lending_setup
+        .set_asset_supply_cap(USDC_TOKEN_ID, 5_000)
+        .assert_ok();
+
+    lending_setup.check_market(
+        USDC_TOKEN_ID,
+        400,
+        600,
+        LIQ_THRESOLD / 2,
+        Some(USDC_PRICE_IN_DOLLARS),
+        5_000,
+    );
+    lending_setup.check_market(EGLD_TOKEN_ID, 0, 0, 0, Some(EGLD_PRICE_IN_DOLLARS), 0);
+}
+
+#[test]
+fn supply_and_borrow_caps_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(2_000));
+
+    lending_setup
+        .set_asset_supply_cap(MEX_TOKEN_ID, 1_500)
+        .assert_user_error("no pool found for this asset");
+    lending_setup
+        .set_asset_supply_cap(USDC_TOKEN_ID, 1_500)
+        .assert_ok();
+
+    lending_setup.add_collateral_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 1_000);
+    lending_setup
+        .b_mock
+        .execute_esdt_multi_transfer(
+            &user_addr,
+            &lending_setup.lending_pool_wrapper,
+            &[
+                TxTokenTransfer {
+                    token_identifier: ACCOUNT_TOKEN.to_vec(),
+                    nonce: account_nonce,
+                    value: rust_biguint!(1),
+                },
+                TxTokenTransfer {
+                    token_identifier: USDC_TOKEN_ID.to_vec(),
+                    nonce: 0,
+                    value: rust_biguint!(501),
+                },
+            ],
+            |sc| {
+                sc.add_collateral();
+            },
+        )
+        .assert_user_error("supply cap reached");
+
+    lending_setup
+        .set_asset_loan_to_value(USDC_TOKEN_ID, LIQ_THRESOLD / 2)
+        .assert_ok();
+    lending_setup
+        .set_asset_borrow_cap(USDC_TOKEN_ID, 100)
+        .assert_ok();
+    lending_setup
+        .b_mock
+        .execute_esdt_transfer(
+            &user_addr,
+            &lending_setup.lending_pool_wrapper,
+            ACCOUNT_TOKEN,
+            account_nonce,
+            &rust_biguint!(1),
+            |sc| {
+                sc.borrow(managed_token_id!(USDC_TOKEN_ID), managed_biguint!(101));
+            },
+        )
+        .assert_user_error("borrow cap reached");
+}
+
+#[test]
+fn isolation_mode_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let lender_addr = lending_setup.second_user_addr.clone();
+    let user_nonce = lending_setup.enter_market(&user_addr);
+    let lender_nonce = lending_setup.enter_market(&lender_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, EGLD_TOKEN_ID, &rust_biguint!(10));
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(500));
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&lender_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&lender_addr, EGLD_TOKEN_ID, &rust_biguint!(1));
+    for token_id in [USDC_TOKEN_ID, EGLD_TOKEN_ID] {
+        lending_setup
+            .set_liquidation_threshold(token_id, 70 * PERCENT)
+            .assert_ok();
+        lending_setup
+            .set_asset_loan_to_value(token_id, 50 * PERCENT)
+            .assert_ok();
+    }
+    lending_setup
+        .set_asset_isolated(MEX_TOKEN_ID, true)
+        .assert_user_error("no pool found for this asset");
+    lending_setup
+        .set_asset_isolated(EGLD_TOKEN_ID, true)
+        .assert_ok();
+    // a ceiling of 3 USDC, listed here with as many decimals as the ceiling
+    let unit = 10u64.pow(DEBT_CEILING_DECIMALS as u32);
+    lending_setup
+        .set_asset_debt_ceiling(EGLD_TOKEN_ID, 3 * unit)
+        .assert_ok();
+
+    lending_setup.add_collateral_through_lending(&lender_addr, USDC_TOKEN_ID, lender_nonce, 1_000);
+    lending_setup
+        .add_collateral_multi(&lender_addr, lender_nonce, &[(EGLD_TOKEN_ID, 1)])
+        .assert_user_error("isolated collateral cannot be combined with other collateral");
+
+    lending_setup.add_collateral_through_lending(&user_addr, EGLD_TOKEN_ID, user_nonce, 10);
+    lending_setup
+        .add_collateral_multi(&user_addr, user_nonce, &[(USDC_TOKEN_ID, 500)])
+        .assert_user_error("isolated collateral cannot be combined with other collateral");
+
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, user_nonce, 100)
+        .assert_user_error("asset not borrowable in isolation mode");
+    // borrowed amounts are normalised with the decimals of the asset
+    lending_setup
+        .set_isolation_borrowable(USDC_TOKEN_ID, true)
+        .assert_user_error("asset decimals not set");
+    lending_setup
+        .set_asset_oracle(USDC_TOKEN_ID, b"", DEBT_CEILING_DECIMALS)
+        .assert_ok();
+    lending_setup
+        .set_isolation_borrowable(USDC_TOKEN_ID, true)
+        .assert_ok();
+    lending_setup
+        .set_asset_oracle(USDC_TOKEN_ID, b"", 0)
+        .assert_ok();
+    lending_setup
+        .set_asset_debt_ceiling(EGLD_TOKEN_ID, 3 * unit)
+        .assert_user_error("asset decimals not set");
+    lending_setup
+        .set_asset_oracle(USDC_TOKEN_ID, b"", DEBT_CEILING_DECIMALS)
+        .assert_ok();
+
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, user_nonce, 301)
+        .assert_user_error("debt ceiling reached");
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, user_nonce, 300)
+        .assert_ok();
+    lending_setup.check_isolated_asset_debt(EGLD_TOKEN_ID, 3 * unit);
+    lending_setup.check_isolated_account_debt(user_nonce, USDC_TOKEN_ID, 300);
+
+    lending_setup
+        .repay_on_behalf(&user_addr, USDC_TOKEN_ID, user_nonce, 100)
+        .assert_ok();
+    lending_setup.check_isolated_asset_debt(EGLD_TOKEN_ID, 2 * unit);
+
+    // the debt leaves the ceiling it was taken against, even once the collateral no longer
+    // isolates the account, and the overpayment is refunded without counting against it
+    lending_setup
+        .set_asset_isolated(EGLD_TOKEN_ID, false)
+        .assert_ok();
+    lending_setup
+        .repay_on_behalf(&user_addr, USDC_TOKEN_ID, user_nonce, 250)
+        .assert_ok();
+    lending_setup.check_isolated_asset_debt(EGLD_TOKEN_ID, 0);
+    lending_setup.check_isolated_account_debt(user_nonce, USDC_TOKEN_ID, 0);
+    lending_setup.check_borrow_position(user_nonce, USDC_TOKEN_ID, None);
+}
+
+#[test]
+fn isolation_mode_debt_principal_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let lender_addr = lending_setup.second_user_addr.clone();
+    let user_nonce = lending_setup.enter_market(&user_addr);
+    let lender_nonce = lending_setup.enter_market(&lender_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, EGLD_TOKEN_ID, &rust_biguint!(1_000_000));
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(100_000_000));
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&lender_addr, USDC_TOKEN_ID, &rust_biguint!(100_000_000));
+    for token_id in [USDC_TOKEN_ID, EGLD_TOKEN_ID] {
+        lending_setup
+            .set_liquidation_threshold(token_id, 70 * PERCENT)
+            .assert_ok();
+        lending_setup
+            .set_asset_loan_to_value(token_id, 50 * PERCENT)
+            .assert_ok();
+    }
+    lending_setup
+        .set_interest_rate_model(USDC_TOKEN_ID, || InterestRateModel::Fixed {
+            rate: managed_biguint!(PERCENT),
+        })
+        .assert_ok();
+    lending_setup
+        .set_asset_oracle(USDC_TOKEN_ID, b"", 6)
+        .assert_ok();
+    lending_setup
+        .set_asset_isolated(EGLD_TOKEN_ID, true)
+        .assert_ok();
+    lending_setup
+        .set_isolation_borrowable(USDC_TOKEN_ID, true)
+        .assert_ok();
+
+    lending_setup.add_collateral_through_lending(
+        &lender_addr,
+        USDC_TOKEN_ID,
+        lender_nonce,
+        100_000_000,
+    );
+    lending_setup.add_collateral_through_lending(&user_addr, EGLD_TOKEN_ID, user_nonce, 1_000_000);
+
+    // without a debt ceiling, isolated accounts borrow freely
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, user_nonce, 50_000_000)
+        .assert_ok();
+    let unit = 10u64.pow(DEBT_CEILING_DECIMALS as u32);
+    lending_setup.check_isolated_asset_debt(EGLD_TOKEN_ID, 50 * unit);
+    lending_setup.check_isolated_account_debt(user_nonce, USDC_TOKEN_ID, 50_000_000);
+
+    lending_setup.b_mock.set_block_round(10);
+    lending_setup
+        .accrue_account(&user_addr, user_nonce)
+        .assert_ok();
+    let mut owed = 0;
+    lending_setup
+        .b_mock
+        .execute_query(&lending_setup.lending_pool_wrapper, |sc| {
+            owed = sc
+                .borrow_positions(user_nonce)
+                .get(&managed_token_id!(USDC_TOKEN_ID))
+                .unwrap()
+                .amount
+                .to_u64()
+                .unwrap();
+        })
+        .assert_ok();
+    assert!(owed > 50_000_000);
+
+    // repaying the interest leaves the principal counted against the ceiling
+    lending_setup
+        .repay_on_behalf(&user_addr, USDC_TOKEN_ID, user_nonce, owed - 50_000_000)
+        .assert_ok();
+    lending_setup.check_isolated_asset_debt(EGLD_TOKEN_ID, 50 * unit);
+    lending_setup.check_isolated_account_debt(user_nonce, USDC_TOKEN_ID, 50_000_000);
+
+    lending_setup
+        .repay_on_behalf(&user_addr, USDC_TOKEN_ID, user_nonce, 20_000_000)
+        .assert_ok();
+    lending_setup.check_isolated_asset_debt(EGLD_TOKEN_ID, 30 * unit);
+    lending_setup.check_isolated_account_debt(user_nonce, USDC_TOKEN_ID, 30_000_000);
+}
+
+#[test]
+fn e_mode_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let liquidator_addr = lending_setup.second_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_400));
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, EGLD_TOKEN_ID, &rust_biguint!(1));
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&liquidator_addr, USDC_TOKEN_ID, &rust_biguint!(300));
+    for token_id in [USDC_TOKEN_ID, EGLD_TOKEN_ID] {
+        lending_setup
+            .set_liquidation_threshold(token_id, 70 * PERCENT)
+            .assert_ok();
+        lending_setup
+            .set_asset_loan_to_value(token_id, 50 * PERCENT)
+            .assert_ok();
+    }
+
+    lending_setup
+        .set_e_mode_category(0, 90 * PERCENT, 95 * PERCENT, 2 * PERCENT)
+        .assert_user_error("e-mode category 0 is reserved");
+    lending_setup
+        .set_e_mode_category(1, 95 * PERCENT, 90 * PERCENT, 2 * PERCENT)
+        .assert_user_error("loan_to_value must be lower than liquidation_threshold");
+    lending_setup
+        .set_e_mode_category(1, 90 * PERCENT, 95 * PERCENT, 2 * PERCENT)
+        .assert_ok();
+    lending_setup
+        .set_asset_e_mode_category(USDC_TOKEN_ID, 2)
+        .assert_user_error("e-mode category not found");
+    lending_setup
+        .set_asset_e_mode_category(USDC_TOKEN_ID, 1)
+        .assert_ok();
+
+    lending_setup.add_collateral_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 1_000);
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 800)
+        .assert_user_error("Not enough collateral available for this loan!");
+    lending_setup
+        .set_account_e_mode_category(&user_addr, account_nonce, 2)
+        .assert_user_error("e-mode category not found");
+    lending_setup
+        .set_account_e_mode_category(&user_addr, account_nonce, 1)
+        .assert_ok();
+    lending_setup
+        .borrow_through_lending(&user_addr, USDC_TOKEN_ID, account_nonce, 800)
+        .assert_ok();
+
+    // collateral outside the category falls back to the per-asset parameters
+    lending_setup
+        .add_collateral_multi(&user_addr, account_nonce, &[(EGLD_TOKEN_ID, 1)])
+        .assert_user_error("Not enough collateral available for this loan!");
+    lending_setup
+        .set_account_e_mode_category(&user_addr, account_nonce, 0)
+        .assert_user_error("Not enough collateral available for this loan!");
+
+    // 1000 * 95% / 800 is healthy under the category threshold
+    lending_setup
+        .try_liquidate(&liquidator_addr, account_nonce, USDC_TOKEN_ID, 300, BP / 2)
+        .assert_user_error("health not low enough for liquidation");
+    assert_eq!(lending_setup.get_liquidation_candidates(1, 10, BP), vec![]);
+    assert_eq!(
+        lending_setup.get_liquidation_candidates(1, 10, 120 * PERCENT),
+        vec![(
+            account_nonce,
+            11_875 * (PERCENT / 100),
+            1_000 * USDC_PRICE_IN_DOLLARS,
+            800 * USDC_PRICE_IN_DOLLARS,
+        )]
+    );
+
+    // the health of the account is checked once all the collaterals are added
+    lending_setup
+        .add_collateral_multi(
+            &user_addr,
+            account_nonce,
+            &[(EGLD_TOKEN_ID, 1), (USDC_TOKEN_ID, 400)],
+        )
+        .assert_ok();
+
+    // anyone can still top up the collateral of an account that is no longer healthy
+    lending_setup
+        .set_asset_loan_to_value(USDC_TOKEN_ID, 10 * PERCENT)
+        .assert_ok();
+    lending_setup
+        .set_liquidation_threshold(USDC_TOKEN_ID, 20 * PERCENT)
+        .assert_ok();
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&liquidator_addr, EGLD_TOKEN_ID, &rust_biguint!(1));
+    lending_setup
+        .add_collateral_on_behalf(&liquidator_addr, EGLD_TOKEN_ID, account_nonce, 1)
+        .assert_ok();
+    lending_setup.check_deposit_position(account_nonce, EGLD_TOKEN_ID, Some(2));
+}
+
+#[test]
+fn liquidation_candidates_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let healthy_user = lending_setup.first_user_addr.clone();
+    let unhealthy_user = lending_setup.second_user_addr.clone();
+    let lender_user = lending_setup.third_user_addr.clone();
+
+    lending_setup
+        .set_liquidation_threshold(USDC_TOKEN_ID, 70 * PERCENT)
+        .assert_ok();
+
+    let healthy_nonce = lending_setup.enter_market(&healthy_user);
+    let unhealthy_nonce = lending_setup.enter_market(&unhealthy_user);
+    let lender_nonce = lending_setup.enter_market(&lender_user);
+    lending_setup.set_account_positions(
+        healthy_nonce,
+        &[(USDC_TOKEN_ID, 1_000)],
+        &[(USDC_TOKEN_ID, 600)],
+    );
+    lending_setup.set_account_positions(
+        unhealthy_nonce,
+        &[(USDC_TOKEN_ID, 1_000)],
+        &[(USDC_TOKEN_ID, 800)],
+    );
+    lending_setup.set_account_positions(lender_nonce, &[(USDC_TOKEN_ID, 1_000)], &[]);
+
+    // 1000 * 70% / 800
+    let unhealthy_candidate = (
+        unhealthy_nonce,
+        875 * PERCENT / 10,
+        1_000 * USDC_PRICE_IN_DOLLARS,
+        800 * USDC_PRICE_IN_DOLLARS,
+    );
+    assert_eq!(
+        lending_setup.get_liquidation_candidates(1, 10, BP),
+        vec![unhealthy_candidate]
+    );
+
+    // 1000 * 70% / 600 is below 120%
+    let candidates = lending_setup.get_liquidation_candidates(1, 10, 120 * PERCENT);
+    assert_eq!(candidates.len(), 2);
+    assert_eq!(candidates[0].0, healthy_nonce);
+
+    // pages only scan their own accounts
+    assert_eq!(lending_setup.get_liquidation_candidates(1, 1, BP), vec![]);
+    assert_eq!(
+        lending_setup.get_liquidation_candidates(2, 2, BP),
+        vec![unhealthy_candidate]
+    );
+    assert_eq!(lending_setup.get_liquidation_candidates(4, 10, BP), vec![]);
+
+    lending_setup.check_liquidation_candidates_error(0, 10, "from_index starts at 1");
+    lending_setup.check_liquidation_candidates_error(1, 0, "invalid page size");
+    lending_setup.check_liquidation_candidates_error(1, 51, "invalid page size");
+}
+
+#[test]
+fn accrue_all_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let keeper_addr = lending_setup.first_user_addr.clone();
+
+    lending_setup.set_pool_utilisation(USDC_TOKEN_ID, 400, 600);
+    lending_setup.set_pool_utilisation(EGLD_TOKEN_ID, 900, 100);
+    let usdc_borrow_rate = lending_setup.get_borrow_rate(USDC_TOKEN_ID);
+    let egld_borrow_rate = lending_setup.get_borrow_rate(EGLD_TOKEN_ID);
+
+    lending_setup.b_mock.set_block_round(10);
+    lending_setup.accrue_all(&keeper_addr).assert_ok();
+    lending_setup.check_pool_indexes(USDC_TOKEN_ID, BP + 10 * usdc_borrow_rate, LIQ_THRESOLD);
+    lending_setup.check_pool_indexes(EGLD_TOKEN_ID, BP + 10 * egld_borrow_rate, LIQ_THRESOLD);
+
+    // a single pool can be accrued directly by anyone
+    lending_setup.b_mock.set_block_round(20);
+    lending_setup
+        .b_mock
+        .execute_tx(
+            &keeper_addr,
+            &lending_setup.liquidity_pool_usdc_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.update_interest_indexes();
+            },
+        )
+        .assert_ok();
+    // the index compounds on top of the first accrual
+    let usdc_index = BP + 10 * usdc_borrow_rate;
+    lending_setup.check_pool_indexes(
+        USDC_TOKEN_ID,
+        usdc_index + (usdc_index as u128 * 10 * usdc_borrow_rate as u128 / BP as u128) as u64,
+        LIQ_THRESOLD,
+    );
+    lending_setup.check_pool_indexes(EGLD_TOKEN_ID, BP + 10 * egld_borrow_rate, LIQ_THRESOLD);
+}
+
+#[test]
+fn upgrade_liquidity_pool_keeps_state_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+
+    lending_setup.add_collateral(&user_addr, USDC_TOKEN_ID, 0, account_nonce, 1000, 1000);
+    lending_setup.borrow(&user_addr, USDC_TOKEN_ID, 0, account_nonce, 600, 400, 600);
+
+    let borrow_rate = lending_setup.get_borrow_rate(USDC_TOKEN_ID);
+    lending_setup.b_mock.set_block_round(10);
+
+    lending_setup
+        .reinit_liquidity_pool(USDC_TOKEN_ID, EGLD_TOKEN_ID)
+        .assert_user_error("pool asset cannot be changed on upgrade");
+    lending_setup
+        .reinit_liquidity_pool(USDC_TOKEN_ID, USDC_TOKEN_ID)
+        .assert_ok();
+
+    lending_setup.check_pool_indexes(USDC_TOKEN_ID, BP + 10 * borrow_rate, LIQ_THRESOLD);
+    lending_setup.check_pool_storage_version(USDC_TOKEN_ID, 2);
+}
+
+#[test]
+fn compounding_index_migration_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+
+    // a pool whose additive indexes grew by 10% and 20%, and positions opened halfway
+    lending_setup.reset_pool_storage_version(USDC_TOKEN_ID);
+    lending_setup.set_pool_indexes(USDC_TOKEN_ID, BP + BP / 10, BP + BP / 5);
+    lending_setup
+        .b_mock
+        .execute_tx(
+            &lending_setup.owner_addr,
+            &lending_setup.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.deposit_positions(account_nonce).insert(
+                    managed_token_id!(USDC_TOKEN_ID),
+                    DepositPosition::new(
+                        managed_token_id!(USDC_TOKEN_ID),
+                        managed_biguint!(1_000_000),
+                        account_nonce,
+                        0,
+                        managed_biguint!(BP + BP / 20),
+                    ),
+                );
+                sc.borrow_positions(account_nonce).insert(
+                    managed_token_id!(USDC_TOKEN_ID),
+                    BorrowPosition::new(
+                        managed_token_id!(USDC_TOKEN_ID),
+                        managed_biguint!(400_000),
+                        account_nonce,
+                        0,
+                        managed_biguint!(BP + BP / 10),
+                    ),
+                );
+            },
+        )
+        .assert_ok();
+
+    lending_setup
+        .reinit_liquidity_pool(USDC_TOKEN_ID, USDC_TOKEN_ID)
+        .assert_ok();
+    lending_setup.check_pool_storage_version(USDC_TOKEN_ID, 2);
+
+    // the interest accrued on the additive indexes is kept, not measured against the compounding ones
+    lending_setup
+        .accrue_account(&user_addr, account_nonce)
+        .assert_ok();
+    lending_setup.check_deposit_position(account_nonce, USDC_TOKEN_ID, Some(1_050_000));
+    lending_setup.check_borrow_position(account_nonce, USDC_TOKEN_ID, Some(440_000));
+
+    // rebased positions accrue like any other from there on
+    lending_setup
+        .accrue_account(&user_addr, account_nonce)
+        .assert_ok();
+    lending_setup.check_deposit_position(account_nonce, USDC_TOKEN_ID, Some(1_050_000));
+    lending_setup.check_borrow_position(account_nonce, USDC_TOKEN_ID, Some(440_000));
+}
+
+#[test]
+fn upgrade_liquidity_pool_risk_params_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+
+    lending_setup.b_mock.set_block_timestamp(1_000);
+    lending_setup
+        .b_mock
+        .execute_tx(
+            &lending_setup.risk_admin_addr,
+            &lending_setup.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.set_liquidation_threshold(
+                    managed_token_id!(USDC_TOKEN_ID),
+                    managed_biguint!(70 * PERCENT),
+                );
+                assert_events_emitted(&emitted_events(), &[b"update_asset_liquidation_threshold"]);
+            },
+        )
+        .assert_ok();
+    lending_setup
+        .set_asset_loan_to_value(USDC_TOKEN_ID, 60 * PERCENT)
+        .assert_ok();
+    lending_setup
+        .upgrade_liquidity_pool(USDC_TOKEN_ID, 60 * PERCENT)
+        .assert_user_error("loan_to_value must be lower than liquidation_threshold");
+
+    lending_setup
+        .ramp_asset_risk_params(USDC_TOKEN_ID, 40 * PERCENT, 50 * PERCENT, 2_000)
+        .assert_ok();
+    lending_setup.b_mock.set_block_timestamp(1_500);
+    lending_setup
+        .upgrade_liquidity_pool(USDC_TOKEN_ID, 80 * PERCENT)
+        .assert_user_error("risk params ramp in progress");
+
+    // a finished ramp is settled, and its loan to value checked against the new threshold
+    lending_setup.b_mock.set_block_timestamp(2_000);
+    lending_setup
+        .upgrade_liquidity_pool(USDC_TOKEN_ID, 40 * PERCENT)
+        .assert_user_error("loan_to_value must be lower than liquidation_threshold");
+    lending_setup
+        .b_mock
+        .execute_tx(
+            &lending_setup.owner_addr,
+            &lending_setup.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                // the pool upgrade ends the execution, signalling the result of the call so far
+                let signal = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    sc.upgrade_liquidity_pool(
+                        managed_token_id!(USDC_TOKEN_ID),
+                        managed_biguint!(R_BASE),
+                        managed_biguint!(R_SLOPE1),
+                        managed_biguint!(R_SLOPE2),
+                        managed_biguint!(U_OPTIMAL),
+                        managed_biguint!(RESERVE_FACTOR),
+                        managed_biguint!(45 * PERCENT),
+                    )
+                }))
+                .unwrap_err();
+                let events: Vec<Vec<u8>> = signal
+                    .downcast_ref::<TxResult>()
+                    .unwrap()
+                    .result_logs
+                    .iter()
+                    .map(|log| log.topics[0].clone())
+                    .collect();
+                assert_events_emitted(&events, &[b"update_asset_liquidation_threshold"]);
+                std::panic::resume_unwind(signal);
+            },
+        )
+        .assert_ok();
+    lending_setup.check_asset_risk_params(
+        USDC_TOKEN_ID,
+        (40 * PERCENT, 45 * PERCENT),
+        (40 * PERCENT, 45 * PERCENT),
+        0,
+    );
+    lending_setup.check_pool_indexes(USDC_TOKEN_ID, BP, 45 * PERCENT);
+}
+
+#[test]
+fn upgrade_all_liquidity_pools_test() {
+    let mut lending_setup = LendingSetup::deploy_lending(
+        lending_pool::contract_obj,
+        liquidity_pool::contract_obj,
+        aggregator_mock::contract_obj,
+    );
+    let user_addr = lending_setup.first_user_addr.clone();
+    let account_nonce = lending_setup.enter_market(&user_addr);
+
+    lending_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+
+    lending_setup.add_collateral(&user_addr, USDC_TOKEN_ID, 0, account_nonce, 1000, 1000);
+    lending_setup.borrow(&user_addr, USDC_TOKEN_ID, 0, account_nonce, 600, 400, 600);
+
+    let borrow_rate = lending_setup.get_borrow_rate(USDC_TOKEN_ID);
+    lending_setup.b_mock.set_block_round(10);
+
+    // both pools were deployed before storage versioning
+    lending_setup.reset_pool_storage_version(USDC_TOKEN_ID);
+    lending_setup.reset_pool_storage_version(EGLD_TOKEN_ID);
+
+    lending_setup
+        .continue_pools_upgrade(&user_addr)
+        .assert_user_error("no pools upgrade in progress");
+
+    // the first pool is upgraded right away, and its upgrade ends the execution
+    lending_setup
+        .b_mock
+        .execute_tx(
+            &lending_setup.owner_addr,
+            &lending_setup.lending_pool_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                let signal = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    sc.upgrade_all_liquidity_pools()
+                }))
+                .unwrap_err();
+                assert!(signal
+                    .downcast_ref::<TxResult>()
+                    .unwrap()
+                    .pending_calls
+                    .async_call
+                    .is_some());
+                assert_eq!(sc.pools_pending_upgrade().len(), 1);
+                std::panic::resume_unwind(signal);
+            },
+        )
+        .assert_ok();
+    lending_setup.check_pools_pending_upgrade(1);
+    lending_setup.check_pool_storage_version(USDC_TOKEN_ID, 2);
+    lending_setup.check_pool_indexes(USDC_TOKEN_ID, BP + 10 * borrow_rate, LIQ_THRESOLD);
+
+    lending_setup
+        .upgrade_all_liquidity_pools()
+        .assert_user_error("a pools upgrade is already in progress");
+
+    // anyone can carry on the upgrade
+    lending_setup.continue_pools_upgrade(&user_addr).assert_ok();
+    lending_setup.check_pools_pending_upgrade(0);
+    lending_setup.check_pool_storage_version(EGLD_TOKEN_ID, 2);
+    lending_setup.check_pool_indexes(EGLD_TOKEN_ID, BP, LIQ_THRESOLD);
 }
 
 // #[test]
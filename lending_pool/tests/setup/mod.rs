@@ -1,4 +1,7 @@
-use multiversx_sc::{storage::mappers::StorageTokenWrapper, types::Address};
+use multiversx_sc::{
+    storage::mappers::StorageTokenWrapper,
+    types::{Address, MultiValueEncoded},
+};
 use multiversx_sc_scenario::{
     managed_address, managed_biguint, managed_buffer, managed_token_id, rust_biguint,
     whitebox::{BlockchainStateWrapper, ContractObjWrapper},
@@ -69,7 +72,10 @@ where
 
     b_mock
         .execute_tx(&owner_addr, &lending_pool_wrapper, &rust_zero, |sc| {
-            sc.init(managed_address!(&lp_template_addr));
+            sc.init(
+                managed_address!(&lp_template_addr),
+                MultiValueEncoded::new(),
+            );
             sc.set_price_aggregator_address(managed_address!(
                 &price_aggregator_wrapper.address_ref()
             ));
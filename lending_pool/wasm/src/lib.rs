@@ -5,9 +5,9 @@
 ////////////////////////////////////////////////////
 
 // Init:                                 1
-// Endpoints:                           28
+// Endpoints:                           72
 // Async Callback (empty):               1
-// Total number of exported functions:  30
+// Total number of exported functions:  74
 
 #![no_std]
 #![feature(lang_items)]
@@ -21,31 +21,75 @@ multiversx_sc_wasm_adapter::endpoints! {
         enter_market
         exit_market
         addCollateral
+        addCollateralMulti
+        addCollateralOnBehalf
+        setAccountEModeCategory
+        enableAsCollateral
+        disableAsCollateral
         removeCollateral
+        removeCollateralMulti
         borrow
+        borrowMulti
+        approveBorrowDelegation
+        borrowOnBehalf
         repay
+        repayMulti
+        repayOnBehalf
         liquidate
+        accrueAccount
         updateCollateralWithInterest
         updateBorrowsWithDebt
         getLiqPoolTemplateAddress
         createLiquidityPool
         upgradeLiquidityPool
+        upgradeAllLiquidityPools
+        continuePoolsUpgrade
+        accrueAll
         setAggregator
+        setPoolParams
+        setLiquidationThreshold
+        setInterestRateModel
+        setRiskAdmin
         setAssetLoanToValue
+        rampAssetRiskParams
+        settleRiskParamsRamp
         setAssetLiquidationBonus
+        setAssetSupplyCap
+        setAssetBorrowCap
+        setAssetCanBorrow
+        setAssetCanBeCollateral
+        setAssetSiloed
+        setAssetIsolated
+        setAssetDebtCeiling
+        setAssetOracle
+        setIsolationBorrowable
+        setEModeCategory
+        setAssetEModeCategory
+        getAssetRiskParams
         getPoolAddress
+        getPoolsPendingUpgrade
         getPoolAllowed
-        getAssetLoanToValue
-        getAssetLiquidationBonus
+        getRiskAdmin
+        getIsolatedAssetDebt
+        getIsolatedAccountDebt
+        getIsolatedDebtCollateral
+        getIsolationBorrowableAssets
+        getEModeCategory
         getAccountToken
         getAccountPositions
         getDepositPositions
         getBorrowPositions
+        getBorrowAllowance
+        getAccountEModeCategory
+        getAssetConfig
+        getStorageVersion
         getCollateralAmountForToken
         getTotalCollateralAvailable
         getTotalBorrowInDollars
         setPriceAggregatorAddress
         getAggregatorAddress
+        getMarkets
+        getLiquidationCandidates
     )
 }
 
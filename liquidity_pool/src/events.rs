@@ -0,0 +1,39 @@
+multiversx_sc::imports!();
+
+use common_structs::{InterestRateModel, PoolParams};
+
+#[multiversx_sc::module]
+pub trait EventsModule {
+    #[event("update_market_state")]
+    fn update_market_state_event(
+        &self,
+        #[indexed] asset: &TokenIdentifier,
+        #[indexed] round: u64,
+        #[indexed] supply_index: &BigUint,
+        #[indexed] borrow_index: &BigUint,
+        #[indexed] reserves: &BigUint,
+        #[indexed] borrowed_amount: &BigUint,
+        #[indexed] rewards_reserves: &BigUint,
+    );
+
+    #[event("update_pool_params")]
+    fn update_pool_params_event(
+        &self,
+        #[indexed] asset: &TokenIdentifier,
+        pool_params: &PoolParams<Self::Api>,
+    );
+
+    #[event("update_liquidation_threshold")]
+    fn update_liquidation_threshold_event(
+        &self,
+        #[indexed] asset: &TokenIdentifier,
+        #[indexed] liquidation_threshold: &BigUint,
+    );
+
+    #[event("update_interest_rate_model")]
+    fn update_interest_rate_model_event(
+        &self,
+        #[indexed] asset: &TokenIdentifier,
+        model: &InterestRateModel<Self::Api>,
+    );
+}
@@ -4,6 +4,7 @@
 multiversx_sc::imports!();
 multiversx_sc::derive_imports!();
 
+pub mod events;
 pub mod liq_math;
 pub use liq_math::*;
 pub mod liquidity;
@@ -12,6 +13,7 @@ pub use common_structs::*;
 pub use common_tokens::*;
 
 pub mod liq_storage;
+use liq_storage::STORAGE_VERSION;
 pub mod liq_utils;
 
 #[multiversx_sc::contract]
@@ -24,6 +26,7 @@ pub trait LiquidityPool:
     + liq_utils::UtilsModule
     + price_aggregator_proxy::PriceAggregatorModule
     + common_checks::ChecksModule
+    + events::EventsModule
 {
     #[init]
     fn init(
@@ -36,18 +39,174 @@ pub trait LiquidityPool:
         reserve_factor: BigUint,
         liquidation_threshold: BigUint,
     ) {
-        self.pool_asset().set(&asset);
-        self.pool_params().set(&PoolParams {
+        if !self.pool_asset().is_empty() {
+            self.upgrade(
+                asset,
+                r_base,
+                r_slope1,
+                r_slope2,
+                u_optimal,
+                reserve_factor,
+                liquidation_threshold,
+            );
+            return;
+        }
+
+        let pool_params = PoolParams {
             r_base,
             r_slope1,
             r_slope2,
             u_optimal,
             reserve_factor,
-        });
+        };
+        self.require_valid_pool_params(&pool_params);
+        self.require_valid_liquidation_threshold(&liquidation_threshold);
+
+        self.pool_asset().set(&asset);
+        self.pool_params().set(&pool_params);
+        self.interest_rate_model().set(InterestRateModel::Kinked);
         self.liquidation_threshold().set(&liquidation_threshold);
         self.borrow_index().set(BigUint::from(BP));
         self.supply_index().set(BigUint::from(BP));
         self.rewards_reserves().set(BigUint::zero());
         self.borrow_index_last_update_round().set(0);
+        self.storage_version().set(STORAGE_VERSION);
+
+        self.update_pool_params_event(&asset, &pool_params);
+        self.update_liquidation_threshold_event(&asset, &liquidation_threshold);
+        self.update_interest_rate_model_event(&asset, &InterestRateModel::Kinked);
+        self.update_last_capital_utilisation();
+        self.emit_market_state();
+    }
+
+    /// The VM calls `init` on upgrade as well. An already initialised pool keeps
+    /// its reserves, indexes and borrowed amount; only the parameters are replaced
+    /// and the storage is migrated to the layout of the new code.
+    fn upgrade(
+        &self,
+        asset: TokenIdentifier,
+        r_base: BigUint,
+        r_slope1: BigUint,
+        r_slope2: BigUint,
+        u_optimal: BigUint,
+        reserve_factor: BigUint,
+        liquidation_threshold: BigUint,
+    ) {
+        require!(
+            self.pool_asset().get() == asset,
+            "pool asset cannot be changed on upgrade"
+        );
+
+        self.migrate_storage();
+
+        let pool_params = PoolParams {
+            r_base,
+            r_slope1,
+            r_slope2,
+            u_optimal,
+            reserve_factor,
+        };
+        self.require_valid_pool_params(&pool_params);
+        self.require_valid_interest_rate_model(&self.interest_rate_model().get(), &pool_params);
+        self.require_valid_liquidation_threshold(&liquidation_threshold);
+
+        // interest accrued so far is computed with the previous parameters
+        self.update_interest_indexes();
+
+        self.pool_params().set(&pool_params);
+        self.liquidation_threshold().set(&liquidation_threshold);
+
+        self.update_pool_params_event(&asset, &pool_params);
+        self.update_liquidation_threshold_event(&asset, &liquidation_threshold);
+    }
+
+    fn migrate_storage(&self) {
+        let storage_version = self.storage_version().get();
+        require!(
+            storage_version <= STORAGE_VERSION,
+            "cannot downgrade to an older storage version"
+        );
+
+        // version 0: pools deployed before pluggable interest rate models
+        if storage_version < 1 && self.interest_rate_model().is_empty() {
+            self.interest_rate_model().set(InterestRateModel::Kinked);
+        }
+
+        // version 1: additive indexes. Positions opened until now are rebased on these
+        // indexes the next time the pool sees them, see `rebase_on_compounding_index`
+        if storage_version < 2 {
+            self.compounding_supply_index()
+                .set(self.supply_index().get());
+            self.compounding_borrow_index()
+                .set(self.borrow_index().get());
+        }
+
+        self.storage_version().set(STORAGE_VERSION);
+    }
+
+    #[only_owner]
+    #[endpoint(setPoolParams)]
+    fn set_pool_params(
+        &self,
+        r_base: BigUint,
+        r_slope1: BigUint,
+        r_slope2: BigUint,
+        u_optimal: BigUint,
+        reserve_factor: BigUint,
+    ) {
+        let pool_params = PoolParams {
+            r_base,
+            r_slope1,
+            r_slope2,
+            u_optimal,
+            reserve_factor,
+        };
+        self.require_valid_pool_params(&pool_params);
+        self.require_valid_interest_rate_model(&self.interest_rate_model().get(), &pool_params);
+
+        // interest accrued so far is computed with the previous parameters
+        self.update_interest_indexes();
+
+        self.pool_params().set(&pool_params);
+        self.update_pool_params_event(&self.pool_asset().get(), &pool_params);
+    }
+
+    #[only_owner]
+    #[endpoint(setLiquidationThreshold)]
+    fn set_liquidation_threshold(&self, liquidation_threshold: BigUint) {
+        self.require_valid_liquidation_threshold(&liquidation_threshold);
+
+        self.update_interest_indexes();
+
+        self.liquidation_threshold().set(&liquidation_threshold);
+        self.update_liquidation_threshold_event(&self.pool_asset().get(), &liquidation_threshold);
+    }
+
+    #[only_owner]
+    #[endpoint(setInterestRateModel)]
+    fn set_interest_rate_model(&self, model: InterestRateModel<Self::Api>) {
+        self.require_valid_interest_rate_model(&model, &self.pool_params().get());
+
+        // interest accrued so far is computed with the previous model
+        self.update_interest_indexes();
+
+        if let InterestRateModel::Adaptive {
+            min_base_rate,
+            max_base_rate,
+            ..
+        } = &model
+        {
+            // the controller starts from the configured base rate, and a retuned one keeps
+            // the rate it drifted to
+            let base_rate = match self.interest_rate_model().get() {
+                InterestRateModel::Adaptive { .. } => self.adaptive_base_rate().get(),
+                _ => self.pool_params().get().r_base,
+            };
+            self.adaptive_base_rate()
+                .set(base_rate.clamp(min_base_rate.clone(), max_base_rate.clone()));
+        }
+
+        self.update_interest_rate_model_event(&self.pool_asset().get(), &model);
+        self.interest_rate_model().set(model);
     }
 }
@@ -1,4 +1,4 @@
-use common_structs::BP;
+use common_structs::{InterestRateModel, PoolParams, BP};
 
 multiversx_sc::imports!();
 
@@ -8,6 +8,72 @@ multiversx_sc::imports!();
 #[multiversx_sc::module]
 pub trait MathModule {
     fn compute_borrow_rate(
+        &self,
+        model: &InterestRateModel<Self::Api>,
+        pool_params: &PoolParams<Self::Api>,
+        u_current: &BigUint,
+    ) -> BigUint {
+        match model {
+            InterestRateModel::Kinked => self.compute_kinked_borrow_rate(
+                &pool_params.r_base,
+                &pool_params.r_slope1,
+                &pool_params.r_slope2,
+                &pool_params.u_optimal,
+                u_current,
+            ),
+            InterestRateModel::JumpRate { u_jump, r_slope3 } => {
+                self.compute_jump_borrow_rate(pool_params, u_jump, r_slope3, u_current)
+            }
+            InterestRateModel::Fixed { rate } => rate.clone(),
+            InterestRateModel::Adaptive { u_target, .. } => self.compute_kinked_borrow_rate(
+                &pool_params.r_base,
+                &pool_params.r_slope1,
+                &pool_params.r_slope2,
+                u_target,
+                u_current,
+            ),
+        }
+    }
+
+    fn compute_adaptive_base_rate(
+        &self,
+        model: &InterestRateModel<Self::Api>,
+        base_rate: &BigUint,
+        u_current: &BigUint,
+        delta_rounds: u64,
+    ) -> BigUint {
+        let (u_target, adjustment_speed, min_base_rate, max_base_rate) = match model {
+            InterestRateModel::Adaptive {
+                u_target,
+                adjustment_speed,
+                min_base_rate,
+                max_base_rate,
+            } => (u_target, adjustment_speed, min_base_rate, max_base_rate),
+            _ => return base_rate.clone(),
+        };
+
+        let new_base_rate = if u_current > u_target {
+            let drift = adjustment_speed * &(u_current - u_target) * delta_rounds / BP;
+            base_rate + &drift
+        } else {
+            let drift = adjustment_speed * &(u_target - u_current) * delta_rounds / BP;
+            if &drift < base_rate {
+                base_rate - &drift
+            } else {
+                BigUint::zero()
+            }
+        };
+
+        if &new_base_rate < min_base_rate {
+            min_base_rate.clone()
+        } else if &new_base_rate > max_base_rate {
+            max_base_rate.clone()
+        } else {
+            new_base_rate
+        }
+    }
+
+    fn compute_kinked_borrow_rate(
         &self,
         r_base: &BigUint,
         r_slope1: &BigUint,
@@ -27,6 +93,32 @@ pub trait MathModule {
         }
     }
 
+    fn compute_jump_borrow_rate(
+        &self,
+        pool_params: &PoolParams<Self::Api>,
+        u_jump: &BigUint,
+        r_slope3: &BigUint,
+        u_current: &BigUint,
+    ) -> BigUint {
+        let u_optimal = &pool_params.u_optimal;
+        let r_kink = &pool_params.r_base + &pool_params.r_slope1;
+
+        if u_current <= u_optimal {
+            let utilisation_ratio = &(u_current * &pool_params.r_slope1) / u_optimal;
+            &pool_params.r_base + &utilisation_ratio
+        } else if u_current <= u_jump {
+            let denominator = u_jump - u_optimal;
+            let numerator = &(u_current - u_optimal) * &pool_params.r_slope2;
+            r_kink + numerator / denominator
+        } else {
+            let bp = BigUint::from(BP);
+
+            let denominator = &bp - u_jump;
+            let numerator = &(u_current - u_jump) * r_slope3;
+            r_kink + &pool_params.r_slope2 + numerator / denominator
+        }
+    }
+
     fn compute_deposit_rate(
         &self,
         u_current: &BigUint,
@@ -40,6 +132,24 @@ pub trait MathModule {
         deposit_rate / (&bp * &bp * bp)
     }
 
+    /// Compounds a per-round rate over `rounds`, i.e. (1 + rate)^rounds - 1, in BP precision
+    fn compute_compounded_rate(&self, rate_per_round: &BigUint, rounds: u64) -> BigUint {
+        let bp = BigUint::from(BP);
+        let mut factor = &bp + rate_per_round;
+        let mut compounded = bp.clone();
+        let mut remaining_rounds = rounds;
+
+        while remaining_rounds > 0 {
+            if remaining_rounds & 1 == 1 {
+                compounded = &compounded * &factor / &bp;
+            }
+            factor = &factor * &factor / &bp;
+            remaining_rounds >>= 1;
+        }
+
+        compounded - bp
+    }
+
     fn compute_capital_utilisation(
         &self,
         borrowed_amount: &BigUint,
@@ -59,21 +169,20 @@ pub trait MathModule {
         current_supply_index: &BigUint,
         initial_supply_index: &BigUint,
     ) -> BigUint {
-        let bp = BigUint::from(BP);
-        let interest = (current_supply_index - initial_supply_index) * amount / bp;
+        let interest = self.compute_interest(amount, current_supply_index, initial_supply_index);
 
         amount + &interest
     }
 
+    /// Interest on `amount` since the index was at `initial_supply_index`. The index compounds,
+    /// so refreshing a position in between does not change the total.
     fn compute_interest(
         &self,
         amount: &BigUint,
         current_supply_index: &BigUint,
         initial_supply_index: &BigUint,
     ) -> BigUint {
-        let bp = BigUint::from(BP);
-
-        (current_supply_index - initial_supply_index) * amount / bp
+        (current_supply_index - initial_supply_index) * amount / initial_supply_index
     }
 
     fn compute_borrowable_amount(
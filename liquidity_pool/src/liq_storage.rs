@@ -1,7 +1,10 @@
 multiversx_sc::imports!();
 multiversx_sc::derive_imports!();
 
-use common_structs::PoolParams;
+use common_structs::{InterestRateModel, PoolParams};
+
+/// Layout version of the pool storage, bumped whenever an upgrade needs a migration.
+pub const STORAGE_VERSION: u32 = 2;
 
 #[multiversx_sc::module]
 pub trait StorageModule {
@@ -45,6 +48,20 @@ pub trait StorageModule {
     #[storage_mapper("pool_params")]
     fn pool_params(&self) -> SingleValueMapper<PoolParams<Self::Api>>;
 
+    #[view(getInterestRateModel)]
+    #[storage_mapper("interest_rate_model")]
+    fn interest_rate_model(&self) -> SingleValueMapper<InterestRateModel<Self::Api>>;
+
+    #[view(getAdaptiveBaseRate)]
+    #[storage_mapper("adaptive_base_rate")]
+    fn adaptive_base_rate(&self) -> SingleValueMapper<BigUint>;
+
+    /// Capital utilisation after the last state change, which the adaptive base rate
+    /// drifts on until the next index update
+    #[view(getLastCapitalUtilisation)]
+    #[storage_mapper("last_capital_utilisation")]
+    fn last_capital_utilisation(&self) -> SingleValueMapper<BigUint>;
+
     #[view(getTotalBorrow)]
     #[storage_mapper("borrowed_amount")]
     fn borrowed_amount(&self) -> SingleValueMapper<BigUint>;
@@ -64,4 +81,20 @@ pub trait StorageModule {
     #[view(borrowIndexLastUpdateRound)]
     #[storage_mapper("borrow_index_last_update_round")]
     fn borrow_index_last_update_round(&self) -> SingleValueMapper<u64>;
+
+    /// Supply index the pool had when its indexes started compounding, empty for pools
+    /// deployed with compounding indexes
+    #[view(getCompoundingSupplyIndex)]
+    #[storage_mapper("compounding_supply_index")]
+    fn compounding_supply_index(&self) -> SingleValueMapper<BigUint>;
+
+    /// Borrow index the pool had when its indexes started compounding, empty for pools
+    /// deployed with compounding indexes
+    #[view(getCompoundingBorrowIndex)]
+    #[storage_mapper("compounding_borrow_index")]
+    fn compounding_borrow_index(&self) -> SingleValueMapper<BigUint>;
+
+    #[view(getStorageVersion)]
+    #[storage_mapper("storage_version")]
+    fn storage_version(&self) -> SingleValueMapper<u32>;
 }
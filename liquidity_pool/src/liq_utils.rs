@@ -1,13 +1,16 @@
 multiversx_sc::imports!();
 multiversx_sc::derive_imports!();
 
-use crate::{liq_math, liq_storage};
+use crate::{events, liq_math, liq_storage};
 
 use common_structs::*;
 
 #[multiversx_sc::module]
 pub trait UtilsModule:
-    liq_math::MathModule + liq_storage::StorageModule + price_aggregator_proxy::PriceAggregatorModule
+    liq_math::MathModule
+    + liq_storage::StorageModule
+    + events::EventsModule
+    + price_aggregator_proxy::PriceAggregatorModule
 {
     #[view(getCapitalUtilisation)]
     fn get_capital_utilisation(&self) -> BigUint {
@@ -27,9 +30,44 @@ pub trait UtilsModule:
 
     #[view(getDebtInterest)]
     fn get_debt_interest(&self, amount: &BigUint, initial_borrow_index: &BigUint) -> BigUint {
-        let borrow_index_diff = self.get_borrow_index_diff(initial_borrow_index);
+        let (rebased_amount, rebased_index) = self.rebase_on_compounding_index(
+            amount,
+            initial_borrow_index,
+            &self.compounding_borrow_index().get(),
+        );
+        let borrow_index_diff = self.get_borrow_index_diff(&rebased_index);
+
+        &rebased_amount - amount + &rebased_amount * &borrow_index_diff / &rebased_index
+    }
 
-        amount * &borrow_index_diff / BP
+    /// Interest on `amount` since the supply index was at `initial_supply_index`
+    fn get_supply_interest(&self, amount: &BigUint, initial_supply_index: &BigUint) -> BigUint {
+        let (rebased_amount, rebased_index) = self.rebase_on_compounding_index(
+            amount,
+            initial_supply_index,
+            &self.compounding_supply_index().get(),
+        );
+        let supply_index = self.supply_index().get();
+
+        &rebased_amount - amount
+            + self.compute_interest(&rebased_amount, &supply_index, &rebased_index)
+    }
+
+    /// Positions opened while the indexes were additive accrued `amount * index_diff / BP` up
+    /// to the index the pool had when they started compounding. Settles that part, so they
+    /// accrue from there on like any other position.
+    fn rebase_on_compounding_index(
+        &self,
+        amount: &BigUint,
+        initial_index: &BigUint,
+        compounding_index: &BigUint,
+    ) -> (BigUint, BigUint) {
+        if initial_index >= compounding_index {
+            return (amount.clone(), initial_index.clone());
+        }
+
+        let legacy_interest = amount * &(compounding_index - initial_index) / BP;
+        (amount + &legacy_interest, compounding_index.clone())
     }
 
     #[view(getDepositRate)]
@@ -47,29 +85,102 @@ pub trait UtilsModule:
 
     #[view(getBorrowRate)]
     fn get_borrow_rate(&self) -> BigUint {
-        let pool_params = self.pool_params().get();
+        let model = self.interest_rate_model().get();
+        let mut pool_params = self.pool_params().get();
         let capital_utilisation = self.get_capital_utilisation();
 
-        self.compute_borrow_rate(
-            &pool_params.r_base,
-            &pool_params.r_slope1,
-            &pool_params.r_slope2,
-            &pool_params.u_optimal,
-            &capital_utilisation,
-        )
+        if let InterestRateModel::Adaptive { .. } = model {
+            pool_params.r_base = self.adaptive_base_rate().get();
+        }
+
+        self.compute_borrow_rate(&model, &pool_params, &capital_utilisation)
+    }
+
+    /// Borrow rate per round, annualized without compounding
+    #[view(getBorrowApr)]
+    fn get_borrow_apr(&self) -> BigUint {
+        self.get_borrow_rate() * ROUNDS_PER_YEAR
+    }
+
+    /// Borrow rate per round, compounded every round over a year
+    #[view(getBorrowApy)]
+    fn get_borrow_apy(&self) -> BigUint {
+        self.compute_compounded_rate(&self.get_borrow_rate(), ROUNDS_PER_YEAR)
+    }
+
+    /// Supply rate per round, annualized without compounding
+    #[view(getSupplyApr)]
+    fn get_supply_apr(&self) -> BigUint {
+        self.get_supply_rate() * ROUNDS_PER_YEAR
     }
 
+    /// Supply rate per round, compounded every round over a year
+    #[view(getSupplyApy)]
+    fn get_supply_apy(&self) -> BigUint {
+        self.compute_compounded_rate(&self.get_supply_rate(), ROUNDS_PER_YEAR)
+    }
+
+    /// Growth of the supply index per round: the interest paid on the borrowed amount,
+    /// shared among all the supplied capital, as `update_supply_index` accrues it
+    fn get_supply_rate(&self) -> BigUint {
+        let total_amount = self.get_total_supplied_capital();
+        if total_amount == 0 {
+            return BigUint::zero();
+        }
+
+        self.get_borrow_rate() * self.borrowed_amount().get() / total_amount
+    }
+
+    #[view(getAdaptiveRateState)]
+    fn get_adaptive_rate_state(&self) -> AdaptiveRateState<Self::Api> {
+        let u_target = match self.interest_rate_model().get() {
+            InterestRateModel::Adaptive { u_target, .. } => u_target,
+            _ => sc_panic!("pool does not use the adaptive interest rate model"),
+        };
+
+        AdaptiveRateState {
+            base_rate: self.adaptive_base_rate().get(),
+            u_current: self.get_capital_utilisation(),
+            u_target,
+            last_update_round: self.borrow_index_last_update_round().get(),
+        }
+    }
+
+    fn update_adaptive_base_rate(&self, delta_rounds: u64) {
+        let model = self.interest_rate_model().get();
+        if let InterestRateModel::Adaptive { .. } = model {
+            // the rounds since the last update ran at the utilisation recorded back then;
+            // pools upgraded before the snapshot existed fall back to the current one
+            let capital_utilisation = if self.last_capital_utilisation().is_empty() {
+                self.get_capital_utilisation()
+            } else {
+                self.last_capital_utilisation().get()
+            };
+            self.adaptive_base_rate().update(|base_rate| {
+                *base_rate = self.compute_adaptive_base_rate(
+                    &model,
+                    base_rate,
+                    &capital_utilisation,
+                    delta_rounds,
+                )
+            });
+        }
+    }
+
+    /// The indexes compound: each update grows them by the interest accrued on their own value.
     fn update_borrow_index(&self, borrow_rate: &BigUint, delta_rounds: u64) {
-        self.borrow_index()
-            .update(|new_index| *new_index += borrow_rate * delta_rounds);
+        self.borrow_index().update(|new_index| {
+            *new_index = &*new_index * &(borrow_rate * delta_rounds + BP) / BP;
+        });
     }
 
     fn update_supply_index(&self, rewards_increase: BigUint) {
         let total_amount = self.get_total_supplied_capital();
 
         if total_amount != BigUint::zero() {
-            self.supply_index()
-                .update(|new_index| *new_index += rewards_increase * BP / total_amount);
+            self.supply_index().update(|new_index| {
+                *new_index += &*new_index * &rewards_increase / total_amount;
+            });
         }
     }
 
@@ -105,6 +216,8 @@ pub trait UtilsModule:
         current_borrow_index - initial_borrow_index
     }
 
+    /// Accrues interest up to the current round. Public so keepers can keep the indexes fresh.
+    #[endpoint(updateIndexes)]
     fn update_interest_indexes(&self) {
         let borrow_index_last_update_round = self.borrow_index_last_update_round().get();
         let delta_rounds = self.get_round_diff(borrow_index_last_update_round);
@@ -115,10 +228,33 @@ pub trait UtilsModule:
             self.update_borrow_index(&borrow_rate, delta_rounds);
             let rewards_increase = self.update_rewards_reserves(&borrow_rate, delta_rounds);
             self.update_supply_index(rewards_increase);
+            self.update_adaptive_base_rate(delta_rounds);
             self.update_index_last_used();
+            self.update_last_capital_utilisation();
+            self.emit_market_state();
         }
     }
 
+    /// Records the capital utilisation the adaptive base rate drifts on until the next
+    /// index update. Called after every change of the reserves or the borrowed amount.
+    fn update_last_capital_utilisation(&self) {
+        self.last_capital_utilisation()
+            .set(self.get_capital_utilisation());
+    }
+
+    /// Called after every state change
+    fn emit_market_state(&self) {
+        self.update_market_state_event(
+            &self.pool_asset().get(),
+            self.blockchain().get_block_round(),
+            &self.supply_index().get(),
+            &self.borrow_index().get(),
+            &self.reserves().get(),
+            &self.borrowed_amount().get(),
+            &self.rewards_reserves().get(),
+        );
+    }
+
     #[inline]
     fn is_full_repay(
         &self,
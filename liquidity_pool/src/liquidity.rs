@@ -3,6 +3,7 @@ multiversx_sc::derive_imports!();
 
 use common_structs::*;
 
+use super::events;
 use super::liq_math;
 use super::liq_storage;
 use super::liq_utils;
@@ -17,6 +18,7 @@ pub trait LiquidityModule:
     + liq_math::MathModule
     + price_aggregator_proxy::PriceAggregatorModule
     + common_checks::ChecksModule
+    + events::EventsModule
 {
     #[only_owner]
     #[payable("*")]
@@ -26,13 +28,12 @@ pub trait LiquidityModule:
         mut deposit_position: DepositPosition<Self::Api>,
     ) -> DepositPosition<Self::Api> {
         let round = self.blockchain().get_block_round();
-        let supply_index = self.supply_index().get();
 
         self.update_interest_indexes();
+        let supply_index = self.supply_index().get();
 
-        let accrued_interest = self.compute_interest(
+        let accrued_interest = self.get_supply_interest(
             &deposit_position.amount,
-            &supply_index,
             &deposit_position.initial_supply_index,
         );
 
@@ -51,9 +52,9 @@ pub trait LiquidityModule:
         mut borrow_position: BorrowPosition<Self::Api>,
     ) -> BorrowPosition<Self::Api> {
         let round = self.blockchain().get_block_round();
-        let borrow_index = self.borrow_index().get();
 
         self.update_interest_indexes();
+        let borrow_index = self.borrow_index().get();
 
         let accumulated_debt = self.get_debt_interest(
             &borrow_position.amount,
@@ -77,7 +78,6 @@ pub trait LiquidityModule:
         let (deposit_asset, deposit_amount) = self.call_value().single_fungible_esdt();
         let pool_asset = self.pool_asset().get();
         let round = self.blockchain().get_block_round();
-        let supply_index = self.supply_index().get();
         let mut ret_deposit_position = deposit_position.clone();
 
         require!(
@@ -86,6 +86,7 @@ pub trait LiquidityModule:
         );
 
         self.update_interest_indexes();
+        let supply_index = self.supply_index().get();
 
         // Update DepositPosition
         if deposit_position.amount != 0 {
@@ -105,6 +106,9 @@ pub trait LiquidityModule:
         // self.deposit_position().insert(deposit_position);
 
         self.reserves().update(|x| *x += deposit_amount);
+        self.update_last_capital_utilisation();
+        self.emit_market_state();
+
         ret_deposit_position
     }
 
@@ -156,6 +160,8 @@ pub trait LiquidityModule:
 
         self.send()
             .direct_esdt(&initial_caller, &pool_token_id, 0, &borrow_amount);
+        self.update_last_capital_utilisation();
+        self.emit_market_state();
 
         ret_borrow_position
     }
@@ -177,11 +183,8 @@ pub trait LiquidityModule:
         self.update_interest_indexes();
 
         // Withdrawal amount = initial_deposit + Interest
-        let withdrawal_amount = self.compute_withdrawal_amount(
-            &amount,
-            &self.supply_index().get(),
-            &deposit_position.initial_supply_index,
-        );
+        let withdrawal_amount =
+            &amount + &self.get_supply_interest(&amount, &deposit_position.initial_supply_index);
 
         self.reserves().update(|asset_reserve| {
             require!(*asset_reserve >= withdrawal_amount, "insufficient funds");
@@ -192,6 +195,8 @@ pub trait LiquidityModule:
 
         self.send()
             .direct_esdt(&initial_caller, &pool_asset, 0, &withdrawal_amount);
+        self.update_last_capital_utilisation();
+        self.emit_market_state();
 
         deposit_position
     }
@@ -223,7 +228,6 @@ pub trait LiquidityModule:
             let extra_amount = &repay_amount - &total_owed;
             self.send()
                 .direct_esdt(&initial_caller, &repay_asset, 0, &extra_amount);
-            ret_borrow_position.amount -= repay_amount.clone();
             repay_amount = total_owed;
         }
 
@@ -233,6 +237,8 @@ pub trait LiquidityModule:
             .update(|total| *total -= &repay_amount);
 
         self.reserves().update(|total| *total += &repay_amount);
+        self.update_last_capital_utilisation();
+        self.emit_market_state();
 
         ret_borrow_position
     }
@@ -1,6 +1,7 @@
 multiversx_sc::imports!();
 multiversx_sc::derive_imports!();
 
+use super::events;
 use super::liq_math;
 use super::liq_storage;
 use super::liq_utils;
@@ -10,6 +11,7 @@ pub trait TokensModule:
     liq_storage::StorageModule
     + liq_utils::UtilsModule
     + liq_math::MathModule
+    + events::EventsModule
     + price_aggregator_proxy::PriceAggregatorModule
 {
     #[proxy]
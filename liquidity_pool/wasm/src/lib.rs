@@ -5,9 +5,9 @@
 ////////////////////////////////////////////////////
 
 // Init:                                 1
-// Endpoints:                           27
+// Endpoints:                           42
 // Async Callback (empty):               1
-// Total number of exported functions:  29
+// Total number of exported functions:  44
 
 #![no_std]
 #![feature(lang_items)]
@@ -18,17 +18,26 @@ multiversx_sc_wasm_adapter::panic_handler!();
 multiversx_sc_wasm_adapter::endpoints! {
     liquidity_pool
     (
+        setPoolParams
+        setLiquidationThreshold
+        setInterestRateModel
         getPoolAsset
         getReserves
         getRewardsReserves
         getLendToken
         borrowToken
         getPoolParams
+        getInterestRateModel
+        getAdaptiveBaseRate
+        getLastCapitalUtilisation
         getTotalBorrow
         getLiquidationThreshold
         getBorrowIndex
         getSupplyIndex
         borrowIndexLastUpdateRound
+        getCompoundingSupplyIndex
+        getCompoundingBorrowIndex
+        getStorageVersion
         getAccountToken
         getAccountPositions
         updateCollateralWithInterest
@@ -43,6 +52,12 @@ multiversx_sc_wasm_adapter::endpoints! {
         getDebtInterest
         getDepositRate
         getBorrowRate
+        getBorrowApr
+        getBorrowApy
+        getSupplyApr
+        getSupplyApy
+        getAdaptiveRateState
+        updateIndexes
         setPriceAggregatorAddress
         getAggregatorAddress
     )
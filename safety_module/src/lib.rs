@@ -44,6 +44,8 @@ pub trait SafetyModule {
         let nft_token = self.nft_token().get();
         // let nft_nonce = self.mint_deposit_nft(&deposit_metadata, payment.clone());
 
+        self.total_staked().update(|total| *total += &payment);
+
         self.send()
             .direct_esdt(&caller_address, &nft_token, 0, &payment);
     }
@@ -74,6 +76,9 @@ pub trait SafetyModule {
 
         self.convert_wegld(pool_token.clone(), amount.clone());
 
+        self.coverage_paid(&pool_token)
+            .update(|paid| *paid += &amount);
+
         self.send()
             .direct_esdt(&caller_address, &pool_token, 0, &amount);
     }
@@ -87,19 +92,12 @@ pub trait SafetyModule {
         require!(amount > 0, "amount must be greater than 0");
         require!(token_id == self.nft_token().get(), "invalid token");
 
-        let nft_info = self.blockchain().get_esdt_token_data(
-            &self.blockchain().get_sc_address(),
-            &token_id,
-            nft_nonce,
-        );
-
-        let nft_metadata = nft_info.decode_attributes::<DepositPosition<Self::Api>>();
-        let rounds_in_pool = self.blockchain().get_block_round() - nft_metadata.round;
+        let rounds_in_pool = self.get_rounds_in_pool(nft_nonce);
 
         require!(rounds_in_pool > 0, "Invalid round");
 
         let withdraw_amount =
-            self.calculate_amount_for_withdrawal(amount, BigUint::from(rounds_in_pool));
+            self.calculate_amount_for_withdrawal(amount.clone(), BigUint::from(rounds_in_pool));
 
         let wegld_token_id = &self.wegld_token().get();
         let contract_balance = self.blockchain().get_esdt_balance(
@@ -113,12 +111,37 @@ pub trait SafetyModule {
             "the amount withdrawn is too high"
         );
 
+        // positions funded before the counter existed are not part of it
+        self.total_staked().update(|total| {
+            if *total > amount {
+                *total -= &amount;
+            } else {
+                *total = BigUint::zero();
+            }
+        });
+
         self.send()
             .direct_esdt(&caller_address, wegld_token_id, 0, &withdraw_amount);
 
         withdraw_amount
     }
 
+    #[view(getClaimableAmount)]
+    fn get_claimable_amount(&self, nft_nonce: u64, amount: BigUint) -> BigUint {
+        let rounds_in_pool = self.get_rounds_in_pool(nft_nonce);
+
+        self.calculate_amount_for_withdrawal(amount, BigUint::from(rounds_in_pool))
+    }
+
+    #[view(getRemainingCoverageCapacity)]
+    fn get_remaining_coverage_capacity(&self) -> BigUint {
+        self.blockchain().get_esdt_balance(
+            &self.blockchain().get_sc_address(),
+            &self.wegld_token().get(),
+            0,
+        )
+    }
+
     #[only_owner]
     #[endpoint(setLocalRolesNftToken)]
     fn set_local_roles_nft_token(&self, roles: MultiValueEncoded<EsdtLocalRole>) {
@@ -149,6 +172,20 @@ pub trait SafetyModule {
             .call_and_exit();
     }
 
+    fn get_rounds_in_pool(&self, nft_nonce: u64) -> u64 {
+        let nft_info = self.blockchain().get_esdt_token_data(
+            &self.blockchain().get_sc_address(),
+            &self.nft_token().get(),
+            nft_nonce,
+        );
+
+        let nft_metadata = nft_info.decode_attributes::<DepositPosition<Self::Api>>();
+        let current_round = self.blockchain().get_block_round();
+        require!(current_round >= nft_metadata.round, "Invalid round");
+
+        current_round - nft_metadata.round
+    }
+
     fn calculate_amount_for_withdrawal(self, deposit_amount: BigUint, time: BigUint) -> BigUint {
         let percent = (time * self.deposit_apy().get()) / BigUint::from(SECONDS_PER_YEAR);
 
@@ -200,6 +237,14 @@ pub trait SafetyModule {
     #[storage_mapper("deposit_apy")]
     fn deposit_apy(&self) -> SingleValueMapper<BigUint>;
 
+    #[view(getTotalStaked)]
+    #[storage_mapper("total_staked")]
+    fn total_staked(&self) -> SingleValueMapper<BigUint>;
+
+    #[view(getCoveragePaid)]
+    #[storage_mapper("coverage_paid")]
+    fn coverage_paid(&self, pool_token: &TokenIdentifier) -> SingleValueMapper<BigUint>;
+
     #[view(nftToken)]
     #[storage_mapper("nftToken")]
     fn nft_token(&self) -> SingleValueMapper<TokenIdentifier>;
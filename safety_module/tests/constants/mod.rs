@@ -0,0 +1,8 @@
+// safety module constants
+
+pub const SAFETY_MODULE_WASM_PATH: &str = "output/safety-module.wasm";
+pub const WEGLD_TOKEN_ID: &[u8] = b"WEGLD-123456";
+pub const USDC_TOKEN_ID: &[u8] = b"USDC-123456";
+pub const NFT_TOKEN_ID: &[u8] = b"SNFT-abcdef";
+pub const DEPOSIT_APY: u64 = 30_000_000_000_000_000; // 3%
+pub const NFT_NONCE: u64 = 1;
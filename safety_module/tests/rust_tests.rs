@@ -0,0 +1,288 @@
+use constants::*;
+
+use common_structs::{BP, SECONDS_PER_YEAR};
+use multiversx_sc_scenario::{managed_biguint, rust_biguint};
+use safety_module::SafetyModule;
+use safety_module_interaction::SafetySetup;
+
+pub mod constants;
+pub mod safety_module_interaction;
+
+#[test]
+fn setup_all_test() {
+    let _ = SafetySetup::deploy_safety_module(safety_module::contract_obj);
+}
+
+#[test]
+fn fund_test() {
+    let mut safety_setup = SafetySetup::deploy_safety_module(safety_module::contract_obj);
+    let user_addr = safety_setup.first_user_addr.clone();
+    let sc_addr = safety_setup.safety_module_wrapper.address_ref().clone();
+
+    safety_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, WEGLD_TOKEN_ID, &rust_biguint!(1_000));
+    safety_setup
+        .b_mock
+        .set_esdt_balance(&sc_addr, NFT_TOKEN_ID, &rust_biguint!(1_000));
+
+    safety_setup
+        .fund(&user_addr, WEGLD_TOKEN_ID, 600)
+        .assert_ok();
+    safety_setup
+        .fund(&user_addr, WEGLD_TOKEN_ID, 400)
+        .assert_ok();
+
+    safety_setup
+        .b_mock
+        .check_esdt_balance(&user_addr, NFT_TOKEN_ID, &rust_biguint!(1_000));
+    safety_setup
+        .b_mock
+        .check_esdt_balance(&sc_addr, WEGLD_TOKEN_ID, &rust_biguint!(1_000));
+    safety_setup.check_total_staked(1_000);
+}
+
+#[test]
+fn fund_invalid_payment_test() {
+    let mut safety_setup = SafetySetup::deploy_safety_module(safety_module::contract_obj);
+    let user_addr = safety_setup.first_user_addr.clone();
+
+    safety_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+    safety_setup
+        .b_mock
+        .set_esdt_balance(&user_addr, WEGLD_TOKEN_ID, &rust_biguint!(1_000));
+
+    safety_setup
+        .fund(&user_addr, USDC_TOKEN_ID, 1_000)
+        .assert_user_error("invalid token");
+    safety_setup
+        .fund(&user_addr, WEGLD_TOKEN_ID, 0)
+        .assert_user_error("amount must be greater than 0");
+    safety_setup.check_total_staked(0);
+}
+
+#[test]
+fn fund_from_pool_test() {
+    let mut safety_setup = SafetySetup::deploy_safety_module(safety_module::contract_obj);
+    let pool_addr = safety_setup.pool_addr.clone();
+    let sc_addr = safety_setup.safety_module_wrapper.address_ref().clone();
+
+    safety_setup
+        .b_mock
+        .set_esdt_balance(&pool_addr, USDC_TOKEN_ID, &rust_biguint!(500));
+
+    safety_setup
+        .fund_from_pool(&pool_addr, USDC_TOKEN_ID, 0)
+        .assert_user_error("amount must be greater than 0");
+    safety_setup
+        .fund_from_pool(&pool_addr, USDC_TOKEN_ID, 500)
+        .assert_ok();
+
+    safety_setup
+        .b_mock
+        .check_esdt_balance(&sc_addr, USDC_TOKEN_ID, &rust_biguint!(500));
+    safety_setup.check_total_staked(0);
+}
+
+#[test]
+fn take_funds_test() {
+    let mut safety_setup = SafetySetup::deploy_safety_module(safety_module::contract_obj);
+    let pool_addr = safety_setup.pool_addr.clone();
+    let user_addr = safety_setup.first_user_addr.clone();
+    let sc_addr = safety_setup.safety_module_wrapper.address_ref().clone();
+
+    safety_setup
+        .b_mock
+        .set_esdt_balance(&sc_addr, USDC_TOKEN_ID, &rust_biguint!(1_000));
+
+    safety_setup
+        .take_funds(&pool_addr, WEGLD_TOKEN_ID, 100)
+        .assert_user_error("non-existent pool");
+    safety_setup
+        .take_funds(&user_addr, USDC_TOKEN_ID, 100)
+        .assert_user_error("invalid caller address");
+    safety_setup
+        .take_funds(&pool_addr, USDC_TOKEN_ID, 0)
+        .assert_user_error("amount must be greater than 0");
+
+    safety_setup
+        .take_funds(&pool_addr, USDC_TOKEN_ID, 300)
+        .assert_ok();
+    safety_setup
+        .take_funds(&pool_addr, USDC_TOKEN_ID, 200)
+        .assert_ok();
+
+    safety_setup
+        .b_mock
+        .check_esdt_balance(&pool_addr, USDC_TOKEN_ID, &rust_biguint!(500));
+    safety_setup.check_coverage_paid(USDC_TOKEN_ID, 500);
+}
+
+#[test]
+fn take_funds_removed_pool_test() {
+    let mut safety_setup = SafetySetup::deploy_safety_module(safety_module::contract_obj);
+    let owner_addr = safety_setup.owner_addr.clone();
+    let pool_addr = safety_setup.pool_addr.clone();
+
+    safety_setup
+        .b_mock
+        .execute_tx(
+            &owner_addr,
+            &safety_setup.safety_module_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.remove_pool(multiversx_sc_scenario::managed_token_id!(USDC_TOKEN_ID));
+            },
+        )
+        .assert_ok();
+
+    safety_setup
+        .take_funds(&pool_addr, USDC_TOKEN_ID, 100)
+        .assert_user_error("non-existent pool");
+}
+
+#[test]
+fn withdraw_test() {
+    let mut safety_setup = SafetySetup::deploy_safety_module(safety_module::contract_obj);
+    let user_addr = safety_setup.first_user_addr.clone();
+    let sc_addr = safety_setup.safety_module_wrapper.address_ref().clone();
+    let deposit_amount = 1_000_000_000u64;
+    let rounds_in_pool = SECONDS_PER_YEAR / 2;
+
+    safety_setup
+        .b_mock
+        .set_esdt_balance(&sc_addr, WEGLD_TOKEN_ID, &rust_biguint!(2_000_000_000));
+    safety_setup.set_deposit_nft(&user_addr, deposit_amount, 10);
+    safety_setup.b_mock.set_block_round(10 + rounds_in_pool);
+
+    // half a year at 3% APY
+    let expected_amount = deposit_amount + deposit_amount * 15 / 1_000;
+    safety_setup
+        .b_mock
+        .execute_query(&safety_setup.safety_module_wrapper, |sc| {
+            let claimable_amount =
+                sc.get_claimable_amount(NFT_NONCE, managed_biguint!(deposit_amount));
+            assert_eq!(claimable_amount, managed_biguint!(expected_amount));
+        })
+        .assert_ok();
+
+    safety_setup.withdraw(&user_addr, deposit_amount, expected_amount);
+    safety_setup.b_mock.check_esdt_balance(
+        &user_addr,
+        WEGLD_TOKEN_ID,
+        &rust_biguint!(expected_amount),
+    );
+}
+
+#[test]
+fn withdraw_apy_over_rounds_test() {
+    let mut safety_setup = SafetySetup::deploy_safety_module(safety_module::contract_obj);
+    let user_addr = safety_setup.first_user_addr.clone();
+    let sc_addr = safety_setup.safety_module_wrapper.address_ref().clone();
+    let deposit_amount = 1_000_000u64;
+
+    safety_setup
+        .b_mock
+        .set_esdt_balance(&sc_addr, WEGLD_TOKEN_ID, &rust_biguint!(2_000_000));
+    safety_setup.set_deposit_nft(&user_addr, deposit_amount, 1);
+
+    for rounds_in_pool in [1u64, 1_000, SECONDS_PER_YEAR, 2 * SECONDS_PER_YEAR] {
+        safety_setup.b_mock.set_block_round(1 + rounds_in_pool);
+
+        let interest = (rounds_in_pool as u128 * DEPOSIT_APY as u128 / SECONDS_PER_YEAR as u128)
+            * deposit_amount as u128
+            / BP as u128;
+        let expected_amount = deposit_amount + interest as u64;
+
+        safety_setup
+            .b_mock
+            .execute_query(&safety_setup.safety_module_wrapper, |sc| {
+                let claimable_amount =
+                    sc.get_claimable_amount(NFT_NONCE, managed_biguint!(deposit_amount));
+                assert_eq!(claimable_amount, managed_biguint!(expected_amount));
+            })
+            .assert_ok();
+    }
+}
+
+#[test]
+fn withdraw_same_round_test() {
+    let mut safety_setup = SafetySetup::deploy_safety_module(safety_module::contract_obj);
+    let user_addr = safety_setup.first_user_addr.clone();
+    let sc_addr = safety_setup.safety_module_wrapper.address_ref().clone();
+
+    safety_setup
+        .b_mock
+        .set_esdt_balance(&sc_addr, WEGLD_TOKEN_ID, &rust_biguint!(1_000));
+    safety_setup.set_deposit_nft(&user_addr, 1_000, 5);
+    safety_setup.b_mock.set_block_round(5);
+
+    safety_setup
+        .b_mock
+        .execute_esdt_transfer(
+            &user_addr,
+            &safety_setup.safety_module_wrapper,
+            NFT_TOKEN_ID,
+            NFT_NONCE,
+            &rust_biguint!(1_000),
+            |sc| {
+                sc.withdraw();
+            },
+        )
+        .assert_user_error("Invalid round");
+}
+
+#[test]
+fn withdraw_insufficient_balance_test() {
+    let mut safety_setup = SafetySetup::deploy_safety_module(safety_module::contract_obj);
+    let user_addr = safety_setup.first_user_addr.clone();
+
+    safety_setup.set_deposit_nft(&user_addr, 1_000, 1);
+    safety_setup.b_mock.set_block_round(SECONDS_PER_YEAR);
+
+    safety_setup
+        .b_mock
+        .execute_esdt_transfer(
+            &user_addr,
+            &safety_setup.safety_module_wrapper,
+            NFT_TOKEN_ID,
+            NFT_NONCE,
+            &rust_biguint!(1_000),
+            |sc| {
+                sc.withdraw();
+            },
+        )
+        .assert_user_error("the amount withdrawn is too high");
+}
+
+#[test]
+fn change_roles_callback_test() {
+    let mut safety_setup = SafetySetup::deploy_safety_module(safety_module::contract_obj);
+
+    safety_setup.change_roles_callback(Some(b"roles not set"));
+    safety_setup.check_last_error_message(b"roles not set");
+
+    safety_setup.change_roles_callback(None);
+    safety_setup.check_last_error_message(b"");
+}
+
+#[test]
+fn set_local_roles_without_token_test() {
+    let mut safety_setup = SafetySetup::deploy_safety_module(safety_module::contract_obj);
+    let owner_addr = safety_setup.owner_addr.clone();
+
+    safety_setup
+        .b_mock
+        .execute_tx(
+            &owner_addr,
+            &safety_setup.safety_module_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.nft_token().clear();
+                sc.set_local_roles_nft_token(multiversx_sc::types::MultiValueEncoded::new());
+            },
+        )
+        .assert_user_error("No nft token issued");
+}
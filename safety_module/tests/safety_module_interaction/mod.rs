@@ -0,0 +1,217 @@
+use multiversx_sc::{
+    codec::multi_types::OptionalValue,
+    types::{Address, ManagedAsyncCallError, ManagedAsyncCallResult},
+};
+use multiversx_sc_scenario::{
+    managed_address, managed_biguint, managed_buffer, managed_token_id, rust_biguint,
+    whitebox::{BlockchainStateWrapper, ContractObjWrapper, TxResult},
+    DebugApi,
+};
+use safety_module::SafetyModule;
+
+use common_structs::{DepositPosition, BP};
+
+use crate::constants::*;
+
+pub struct SafetySetup<SafetyModuleObjBuilder>
+where
+    SafetyModuleObjBuilder: 'static + Copy + Fn() -> safety_module::ContractObj<DebugApi>,
+{
+    pub owner_addr: Address,
+    pub first_user_addr: Address,
+    pub second_user_addr: Address,
+    pub pool_addr: Address,
+    pub b_mock: BlockchainStateWrapper,
+    pub safety_module_wrapper:
+        ContractObjWrapper<safety_module::ContractObj<DebugApi>, SafetyModuleObjBuilder>,
+}
+
+impl<SafetyModuleObjBuilder> SafetySetup<SafetyModuleObjBuilder>
+where
+    SafetyModuleObjBuilder: 'static + Copy + Fn() -> safety_module::ContractObj<DebugApi>,
+{
+    /* Deploys the Safety Module with a mock WEGLD token and a registered USDC pool */
+    pub fn deploy_safety_module(safety_module_builder: SafetyModuleObjBuilder) -> Self {
+        let rust_zero = rust_biguint!(0u64);
+        let mut b_mock = BlockchainStateWrapper::new();
+        let owner_addr = b_mock.create_user_account(&rust_zero);
+        let first_user_addr = b_mock.create_user_account(&rust_zero);
+        let second_user_addr = b_mock.create_user_account(&rust_zero);
+        let pool_addr = b_mock.create_user_account(&rust_zero);
+
+        let safety_module_wrapper = b_mock.create_sc_account(
+            &rust_zero,
+            Some(&owner_addr),
+            safety_module_builder,
+            SAFETY_MODULE_WASM_PATH,
+        );
+
+        b_mock
+            .execute_tx(&owner_addr, &safety_module_wrapper, &rust_zero, |sc| {
+                sc.init(
+                    managed_token_id!(WEGLD_TOKEN_ID),
+                    managed_biguint!(DEPOSIT_APY),
+                );
+                sc.nft_token().set(&managed_token_id!(NFT_TOKEN_ID));
+                sc.add_pool(
+                    managed_token_id!(USDC_TOKEN_ID),
+                    &managed_address!(&pool_addr),
+                );
+            })
+            .assert_ok();
+
+        Self {
+            owner_addr,
+            first_user_addr,
+            second_user_addr,
+            pool_addr,
+            b_mock,
+            safety_module_wrapper,
+        }
+    }
+
+    pub fn fund(&mut self, user_addr: &Address, token_id: &[u8], amount: u64) -> TxResult {
+        self.b_mock.execute_esdt_transfer(
+            user_addr,
+            &self.safety_module_wrapper,
+            token_id,
+            0,
+            &rust_biguint!(amount),
+            |sc| {
+                sc.fund(OptionalValue::None);
+            },
+        )
+    }
+
+    pub fn fund_from_pool(
+        &mut self,
+        pool_addr: &Address,
+        token_id: &[u8],
+        amount: u64,
+    ) -> TxResult {
+        self.b_mock.execute_esdt_transfer(
+            pool_addr,
+            &self.safety_module_wrapper,
+            token_id,
+            0,
+            &rust_biguint!(amount),
+            |sc| {
+                sc.fund_from_pool();
+            },
+        )
+    }
+
+    pub fn take_funds(&mut self, caller_addr: &Address, token_id: &[u8], amount: u64) -> TxResult {
+        self.b_mock.execute_tx(
+            caller_addr,
+            &self.safety_module_wrapper,
+            &rust_biguint!(0),
+            |sc| {
+                sc.take_funds(managed_token_id!(token_id), managed_biguint!(amount));
+            },
+        )
+    }
+
+    /* Gives the user a deposit NFT, whose attributes are also held by the contract */
+    pub fn set_deposit_nft(&mut self, user_addr: &Address, amount: u64, round: u64) {
+        let _ = DebugApi::dummy();
+        let deposit_position = DepositPosition::<DebugApi>::new(
+            managed_token_id!(WEGLD_TOKEN_ID),
+            managed_biguint!(amount),
+            0,
+            round,
+            managed_biguint!(BP),
+        );
+
+        self.b_mock.set_nft_balance(
+            self.safety_module_wrapper.address_ref(),
+            NFT_TOKEN_ID,
+            NFT_NONCE,
+            &rust_biguint!(1),
+            &deposit_position,
+        );
+        self.b_mock.set_nft_balance(
+            user_addr,
+            NFT_TOKEN_ID,
+            NFT_NONCE,
+            &rust_biguint!(amount),
+            &deposit_position,
+        );
+    }
+
+    pub fn withdraw(&mut self, user_addr: &Address, amount: u64, expected_amount: u64) {
+        self.b_mock
+            .execute_esdt_transfer(
+                user_addr,
+                &self.safety_module_wrapper,
+                NFT_TOKEN_ID,
+                NFT_NONCE,
+                &rust_biguint!(amount),
+                |sc| {
+                    let withdraw_amount = sc.withdraw();
+                    assert_eq!(
+                        withdraw_amount,
+                        managed_biguint!(expected_amount),
+                        "Withdrawn amount doesn't match!"
+                    );
+                },
+            )
+            .assert_ok();
+    }
+
+    pub fn change_roles_callback(&mut self, opt_err_msg: Option<&[u8]>) {
+        self.b_mock
+            .execute_tx(
+                &self.owner_addr,
+                &self.safety_module_wrapper,
+                &rust_biguint!(0),
+                |sc| {
+                    let result = match opt_err_msg {
+                        Some(err_msg) => ManagedAsyncCallResult::Err(ManagedAsyncCallError {
+                            err_code: 4,
+                            err_msg: managed_buffer!(err_msg),
+                        }),
+                        None => ManagedAsyncCallResult::Ok(()),
+                    };
+                    sc.change_roles_callback(result);
+                },
+            )
+            .assert_ok();
+    }
+
+    pub fn check_total_staked(&mut self, expected_total_staked: u64) {
+        self.b_mock
+            .execute_query(&self.safety_module_wrapper, |sc| {
+                assert_eq!(
+                    sc.total_staked().get(),
+                    managed_biguint!(expected_total_staked),
+                    "Total staked in Safety Module doesn't match!"
+                );
+            })
+            .assert_ok();
+    }
+
+    pub fn check_coverage_paid(&mut self, token_id: &[u8], expected_coverage_paid: u64) {
+        self.b_mock
+            .execute_query(&self.safety_module_wrapper, |sc| {
+                assert_eq!(
+                    sc.coverage_paid(&managed_token_id!(token_id)).get(),
+                    managed_biguint!(expected_coverage_paid),
+                    "Coverage paid by Safety Module doesn't match!"
+                );
+            })
+            .assert_ok();
+    }
+
+    pub fn check_last_error_message(&mut self, expected_message: &[u8]) {
+        self.b_mock
+            .execute_query(&self.safety_module_wrapper, |sc| {
+                assert_eq!(
+                    sc.last_error_message().get(),
+                    managed_buffer!(expected_message),
+                    "Last error message doesn't match!"
+                );
+            })
+            .assert_ok();
+    }
+}
@@ -5,9 +5,9 @@
 ////////////////////////////////////////////////////
 
 // Init:                                 1
-// Endpoints:                           12
+// Endpoints:                           16
 // Async Callback:                       1
-// Total number of exported functions:  14
+// Total number of exported functions:  18
 
 #![no_std]
 #![feature(lang_items)]
@@ -24,10 +24,14 @@ multiversx_sc_wasm_adapter::endpoints! {
         fundFromPool
         takeFunds
         withdraw
+        getClaimableAmount
+        getRemainingCoverageCapacity
         setLocalRolesNftToken
         pools
         wegld_token
         deposit_apy
+        getTotalStaked
+        getCoveragePaid
         nftToken
         lastErrorMessage
         callBack